@@ -1,35 +1,187 @@
 use std::any::Any;
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::iter;
 use std::process;
+use std::rc::Rc;
+use std::time::Duration;
 
 use crate::as_any::AsAny;
 use crate::error::Error;
-use crate::row::Row;
-use crate::table::Table;
+use crate::format::{Format, Mode, NumberFormat, ID_WIDTH_MAX};
+use crate::lexer::{at_position, Lexer, TokenKind};
+use crate::query::{Filter, Query};
+use crate::row::{Column, ParseOptions, Row};
+use crate::table::{with_deadline, Table, TABLE_NAME};
 
 /// This function is just a proxy that creates a `Command` or returns an `Error`.
-/// The way it decides if it will return a `MetaCommand` or a `Statement` is
-/// by looking on the `String` `input` if it starts with a dot (`.`).
-fn build_command(input: &str) -> Result<Box<dyn Command>, Error> {
-    if input.chars().next() == Some('.') {
-        MetaCommand::from_str(&input.trim())
+/// Custom commands of the `registry` are matched first, then the way it decides
+/// if it will return a `MetaCommand` or a `Statement` is
+/// by looking on the trimmed `String` `input` if it starts with a dot (`.`).
+///
+/// A single trailing `;` of a statement is ignored, like in `select;`,
+/// and a `;` alone does nothing.
+pub fn build_command(input: &str, registry: &CommandRegistry) -> Result<Box<dyn Command>, Error> {
+    let input = input.trim();
+
+    if let Some(custom_command) = registry.find(input) {
+        return Ok(Box::new(custom_command));
+    }
+
+    if input.starts_with('.') {
+        MetaCommand::from_str(input, registry)
+    } else if input == ";" {
+        Ok(Box::new(Statement::Empty))
     } else {
-        Statement::from_str(&input.trim())
+        let input = input.strip_suffix(';').unwrap_or(input).trim_end();
+
+        Statement::from_str(input)
     }
 }
 
 /// Receives a table and a string, and tries to build the
-/// command and execute it right away
+/// command and execute it right away, with the default `Format`.
+/// Changes to the `Format`, like `.mode insert`, aren't kept.
 pub fn run_command(table: &mut Table, command: String) -> Result<String, Error> {
-    let command_result = build_command(&command);
+    CommandRegistry::new().run_command(table, command)
+}
+
+/// Does the same as `run_command`, but with a `format` that is kept
+/// between the commands, like a `Repl` does.
+pub fn run_command_with_format(
+    table: &mut Table,
+    format: &mut Format,
+    command: String,
+) -> Result<String, Error> {
+    CommandRegistry::new().run_command_with_format(table, format, command)
+}
+
+/// What a command of a script produced, see `execute_script`.
+#[derive(Debug, PartialEq)]
+pub enum CommandOutput {
+    /// The text the command returned, like `Executed.\n`.
+    Output(String),
+    /// The script called `.exit`, with its exit code, nothing after it is run.
+    Exit(i32),
+}
+
+/// Runs a script with one command per line, lazily, yielding what each
+/// command returned. Blank lines are skipped and `.exit` ends the iteration
+/// instead of closing the process, so that it's safe for embedders.
+///
+/// Here's an example:
+/// ```
+/// use scoolite::command::{execute_script, CommandOutput};
+/// use scoolite::table::Table;
+///
+/// let mut table = Table::new();
+/// let script = "insert 1 john john@mailbox.com\n.exit\nselect";
+///
+/// let outputs: Vec<_> = execute_script(&mut table, script).collect();
+///
+/// assert_eq!(outputs, vec![
+///     Ok(CommandOutput::Output("Executed.\n".to_string())),
+///     Ok(CommandOutput::Exit(0)),
+/// ]);
+/// ```
+pub fn execute_script<'a>(
+    table: &'a mut Table,
+    script: &'a str,
+) -> impl Iterator<Item = Result<CommandOutput, Error>> + 'a {
+    let registry = CommandRegistry::new();
+    let mut format = Format::new();
+    let mut lines = script.lines().filter(|line| !line.trim().is_empty());
+    let mut exited = false;
+
+    std::iter::from_fn(move || {
+        if exited {
+            return None;
+        }
+
+        let command = match build_command(lines.next()?, &registry) {
+            Ok(command) => command,
+            Err(error) => return Some(Err(error)),
+        };
+
+        if let (CommandKind::Meta(".exit"), Some(code)) =
+            (command.command_kind(), command.exit_code())
+        {
+            exited = true;
+            return Some(Ok(CommandOutput::Exit(code)));
+        }
+
+        Some(execute_command(command.as_ref(), table, &mut format).map(CommandOutput::Output))
+    })
+}
+
+/// Runs the script at `path` on a new `Table`, returning the exit code of
+/// its `.exit N`, or `0` if it doesn't have one. The process is never
+/// closed, and the first command that fails stops the script.
+pub fn run_script_file(path: &str) -> Result<i32, Error> {
+    let script = fs::read_to_string(path)
+        .map_err(|error| Error::Io(format!("Failed to read '{}': {}", path, error)))?;
+
+    let mut table = Table::new();
+
+    for output in execute_script(&mut table, &script) {
+        if let CommandOutput::Exit(code) = output? {
+            return Ok(code);
+        }
+    }
 
-    try_execute_command(command_result, table)
+    Ok(0)
 }
 
 fn try_execute_command(
     command_result: Result<Box<dyn Command>, Error>,
     table: &mut Table,
+    format: &mut Format,
+) -> Result<String, Error> {
+    execute_command(command_result?.as_ref(), table, format)
+}
+
+/// Executes an already built `command`, unless it's a mutating
+/// one and the `table` is in read-only mode.
+pub fn execute_command(
+    command: &dyn Command,
+    table: &mut Table,
+    format: &mut Format,
 ) -> Result<String, Error> {
-    command_result?.execute(table)
+    if table.is_readonly() && command.is_mutating() {
+        return Err(Error::ReadOnly(format!(
+            "Read-only mode. '{}' can't change the table",
+            command.command_kind()
+        )));
+    }
+
+    with_deadline(table.timeout(), || command.execute(table, format))
+}
+
+/// Executes an already built `command` with shared access to the `table`,
+/// it's `None` if the `command` needs `execute_command`, see `Command::execute_shared`.
+pub fn execute_shared_command(
+    command: &dyn Command,
+    table: &Table,
+    format: &mut Format,
+) -> Option<Result<String, Error>> {
+    with_deadline(table.timeout(), || command.execute_shared(table, format))
+}
+
+/// Consumes the next `Token`s, returning a `SyntaxError` if they aren't `keywords`.
+fn expect_keywords(tokens: &mut Lexer, keywords: &[&str]) -> Result<(), Error> {
+    for keyword in keywords {
+        let position = tokens.next_position();
+
+        if tokens.next().map(|token| token.text) != Some(keyword) {
+            return Err(at_position(
+                Error::SyntaxError(format!("Syntax error. Expected '{}'", keyword)),
+                position,
+            ));
+        }
+    }
+
+    Ok(())
 }
 
 /// Creates an `Error` with the default `"not implemented"` message.
@@ -38,42 +190,594 @@ fn build_not_implemented_error(input: &str) -> Error {
     Error::UnrecognizedStatement(message)
 }
 
+/// Creates the `Error` for inputs starting with a dot that
+/// aren't any `MetaCommand`, like `.` or `.bogus`.
+fn build_unrecognized_meta_command_error(input: &str) -> Error {
+    Error::UnrecognizedCommand(format!("Unrecognized meta command '{}'", input))
+}
+
+/// How each `MetaCommand` is called, by its name.
+const META_COMMAND_USAGES: &[(&str, &str)] = &[
+    (".exit", ".exit [code]"),
+    (".memory", ".memory"),
+    (".cache", ".cache on|off"),
+    (".stats", ".stats"),
+    (".intern", ".intern on|off"),
+    (".dedup", ".dedup on|off"),
+    (".escape", ".escape on|off"),
+    (".trailer", ".trailer on|off"),
+    (".undo", ".undo"),
+    (".readonly", ".readonly on|off"),
+    (".truncate", ".truncate on|off"),
+    (".defaultemail", ".defaultemail <email>"),
+    (".allowdomain", ".allowdomain <domain>"),
+    (".seed", ".seed <seed>"),
+    (".rowsep", ".rowsep <separator>"),
+    (".mode", ".mode list|insert|line"),
+    (".numberformat", ".numberformat dec|hex"),
+    (".comment", ".comment users [text]"),
+    (".maxoutput", ".maxoutput <bytes>"),
+    (".timeout", ".timeout <milliseconds>"),
+    (".histogram", ".histogram <column> <buckets>"),
+    (".peek", ".peek <size>"),
+    (".validate", ".validate <command>"),
+    (".rowid", ".rowid <id>"),
+    (".format", ".format id-width <width>"),
+];
+
+/// Creates the `Error` for a known command called with the wrong
+/// arguments, like `.cache maybe`, telling how it's called.
+pub(crate) fn build_usage_error(usage: &str) -> Error {
+    Error::SyntaxError(format!("Syntax error. Usage: {}", usage))
+}
+
 /// The interface that every `Command` asks for is just an `execute` method, which
-/// executes the specific logic for the `Command`.
+/// executes the specific logic for the `Command`, and a `command_kind` one, which
+/// tells what the `Command` is without having to downcast it. The `format` is the
+/// one of the session running it, to write `Row`s and to be changed.
 pub trait Command: AsAny {
-    fn execute(&self, table: &mut Table) -> Result<String, Error>;
+    fn execute(&self, table: &mut Table, format: &mut Format) -> Result<String, Error>;
+
+    fn command_kind(&self) -> CommandKind;
+
+    /// Executes the `Command` with shared access to the `table`, for the ones
+    /// that only read it, like a `select`, so that many of them can run at once.
+    /// It's `None` for the ones that need `execute`.
+    fn execute_shared(
+        &self,
+        _table: &Table,
+        _format: &mut Format,
+    ) -> Option<Result<String, Error>> {
+        None
+    }
+
+    /// Returns if the `Command` changes the `Table`, which by default
+    /// is what its `command_kind` tells.
+    fn is_mutating(&self) -> bool {
+        self.command_kind().is_mutating()
+    }
+
+    /// The code to exit with, only `.exit` has one.
+    fn exit_code(&self) -> Option<i32> {
+        None
+    }
+}
+
+/// What a `Command` is. Meta commands carry their name, like `".exit"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CommandKind {
+    Meta(&'static str),
+    Insert,
+    Select,
+    Update,
+    Delete,
+    Custom,
+    /// A lone `;`, which does nothing.
+    Empty,
+}
+
+impl CommandKind {
+    /// Returns if `Command`s of this kind change the `Row`s of the `Table`,
+    /// custom commands get the whole `Table`, so they're assumed to.
+    pub fn is_mutating(&self) -> bool {
+        match self {
+            CommandKind::Insert
+            | CommandKind::Update
+            | CommandKind::Delete
+            | CommandKind::Custom
+            | CommandKind::Meta(".undo") => true,
+            CommandKind::Meta(_) | CommandKind::Select | CommandKind::Empty => false,
+        }
+    }
+}
+
+impl fmt::Display for CommandKind {
+    /// Writes the name of the meta command, or the keyword of the statement.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            CommandKind::Meta(name) => name,
+            CommandKind::Insert => "insert",
+            CommandKind::Select => "select",
+            CommandKind::Update => "update",
+            CommandKind::Delete => "delete",
+            CommandKind::Custom => "custom command",
+            CommandKind::Empty => "empty",
+        };
+
+        write!(f, "{}", name)
+    }
 }
 
+/// The maximum amount of buckets of a `.histogram`, one line is written for each of them.
+const HISTOGRAM_MAX_BUCKETS: usize = 1000;
+
 /// `MetaCommand` is the `enum` that contains all meta commands for `scoolite`.
 /// An example of meta command is `.exit`, it does not belong to the `SQL` specification
 /// however it is used to close the program/REPL.
 #[derive(Debug, PartialEq)]
 enum MetaCommand {
-    Exit,
+    /// `.exit [N]`, closes the process with the exit code `N`, `0` by default.
+    Exit(i32),
+    Memory,
+    Cache(bool),
+    Stats,
+    RowSeparator(String),
+    Mode(Mode),
+    IdWidth(usize),
+    ReadOnly(bool),
+    Seed(u64),
+    AllowDomain(String),
+    Undo,
+    NumberFormat(NumberFormat),
+    Dedup(bool),
+    /// Turns escaping the control characters of text values on or off.
+    Escape(bool),
+    /// Turns the `Executed.` line after each statement on or off.
+    Trailer(bool),
+    Intern(bool),
+    DefaultEmail(String),
+    Truncate(bool),
+    MaxOutput(usize),
+    /// Sets the milliseconds a single command can take, `0` removes the limit.
+    Timeout(u64),
+    /// Shows the first and last `N` `Row`s by `id`.
+    Peek(usize),
+    /// Shows the internal `rowid` of the `Row` with the `id` `N`.
+    RowId(usize),
+    /// Parses a command without running it, with the custom commands of the
+    /// registry too, returning its parse error if any. The input of an
+    /// `insert` is kept, since its values are only parsed when it runs.
+    Validate(Option<String>),
+    /// Counts the values of a numeric column in `N` equal ranges.
+    Histogram(Column, usize),
+    /// Sets the description of the `Table`, or shows it when there's no text.
+    Comment(Option<String>),
 }
 
 impl MetaCommand {
-    /// Tries to parse an `&str` `input` into a `Box<Command>`, if
-    /// it fails it returns a `"not implemented error"` `Error`.
+    /// Tries to parse an `&str` `input` into a `Box<Command>`, if it isn't
+    /// any meta command it returns an `UnrecognizedCommand` `Error`, and if
+    /// only its arguments are wrong, a `SyntaxError` with its usage.
     ///
     /// All of the possibilities are just the variants on the `enum`.
-    fn from_str(input: &str) -> Result<Box<dyn Command>, Error> {
-        match input {
-            ".exit" => Ok(Box::new(MetaCommand::Exit)),
-            _ => Err(build_not_implemented_error(input)),
+    fn from_str(input: &str, registry: &CommandRegistry) -> Result<Box<dyn Command>, Error> {
+        let arguments: Vec<&str> = input.split_whitespace().collect();
+
+        match arguments.as_slice() {
+            [".exit"] => Ok(Box::new(MetaCommand::Exit(0))),
+            [".exit", code] => {
+                let code = code.parse::<i32>().map_err(|_| {
+                    Error::SyntaxError(format!(
+                        "Syntax error. Failed to parse 'exit' from '{}'",
+                        code
+                    ))
+                })?;
+
+                Ok(Box::new(MetaCommand::Exit(code)))
+            }
+            [".memory"] => Ok(Box::new(MetaCommand::Memory)),
+            [".cache", "on"] => Ok(Box::new(MetaCommand::Cache(true))),
+            [".cache", "off"] => Ok(Box::new(MetaCommand::Cache(false))),
+            [".stats"] => Ok(Box::new(MetaCommand::Stats)),
+            [".intern", "on"] => Ok(Box::new(MetaCommand::Intern(true))),
+            [".intern", "off"] => Ok(Box::new(MetaCommand::Intern(false))),
+            [".dedup", "on"] => Ok(Box::new(MetaCommand::Dedup(true))),
+            [".dedup", "off"] => Ok(Box::new(MetaCommand::Dedup(false))),
+            [".escape", "on"] => Ok(Box::new(MetaCommand::Escape(true))),
+            [".escape", "off"] => Ok(Box::new(MetaCommand::Escape(false))),
+            [".trailer", "on"] => Ok(Box::new(MetaCommand::Trailer(true))),
+            [".trailer", "off"] => Ok(Box::new(MetaCommand::Trailer(false))),
+            [".undo"] => Ok(Box::new(MetaCommand::Undo)),
+            [".readonly", "on"] => Ok(Box::new(MetaCommand::ReadOnly(true))),
+            [".readonly", "off"] => Ok(Box::new(MetaCommand::ReadOnly(false))),
+            [".truncate", "on"] => Ok(Box::new(MetaCommand::Truncate(true))),
+            [".truncate", "off"] => Ok(Box::new(MetaCommand::Truncate(false))),
+            [".defaultemail", email] => Ok(Box::new(MetaCommand::DefaultEmail(email.to_string()))),
+            [".allowdomain", domain] => Ok(Box::new(MetaCommand::AllowDomain(domain.to_string()))),
+            [".seed", seed] => {
+                let seed = seed.parse::<u64>().map_err(|_| {
+                    Error::SyntaxError(format!(
+                        "Syntax error. Failed to parse 'seed' from '{}'",
+                        seed
+                    ))
+                })?;
+
+                Ok(Box::new(MetaCommand::Seed(seed)))
+            }
+            [".rowsep", separator] => Ok(Box::new(MetaCommand::RowSeparator(
+                separator.replace("\\n", "\n").replace("\\t", "\t"),
+            ))),
+            [".mode", mode] => Ok(Box::new(MetaCommand::Mode(mode.parse::<Mode>()?))),
+            [".numberformat", format] => Ok(Box::new(MetaCommand::NumberFormat(
+                format.parse::<NumberFormat>()?,
+            ))),
+            [".comment", table] if *table == TABLE_NAME => Ok(Box::new(MetaCommand::Comment(None))),
+            [".comment", table, ..] if *table == TABLE_NAME => {
+                let text = input[".comment".len()..].trim_start()[table.len()..].trim();
+                let text = text
+                    .strip_prefix('"')
+                    .and_then(|text| text.strip_suffix('"'))
+                    .unwrap_or(text);
+
+                Ok(Box::new(MetaCommand::Comment(Some(text.to_string()))))
+            }
+            [".comment", table, ..] => Err(Error::SyntaxError(format!(
+                "Syntax error. Unknown table '{}'",
+                table
+            ))),
+            [".maxoutput", bytes] => {
+                let bytes = bytes.parse::<usize>().map_err(|_| {
+                    Error::SyntaxError(format!(
+                        "Syntax error. Failed to parse 'maxoutput' from '{}'",
+                        bytes
+                    ))
+                })?;
+
+                Ok(Box::new(MetaCommand::MaxOutput(bytes)))
+            }
+            [".timeout", milliseconds] => {
+                let milliseconds = milliseconds.parse::<u64>().map_err(|_| {
+                    Error::SyntaxError(format!(
+                        "Syntax error. Failed to parse 'timeout' from '{}'",
+                        milliseconds
+                    ))
+                })?;
+
+                Ok(Box::new(MetaCommand::Timeout(milliseconds)))
+            }
+            [".histogram", column, buckets] => {
+                let column = column.parse::<Column>()?;
+
+                if column != Column::Id && column != Column::RowId {
+                    return Err(Error::SyntaxError(format!(
+                        "Syntax error. '.histogram' requires a numeric column, not '{}'",
+                        column
+                    )));
+                }
+
+                match buckets.parse::<usize>() {
+                    Ok(buckets) if buckets > HISTOGRAM_MAX_BUCKETS => {
+                        Err(Error::SyntaxError(format!(
+                            "Syntax error. '.histogram' allows at most {} buckets",
+                            HISTOGRAM_MAX_BUCKETS
+                        )))
+                    }
+                    Ok(buckets) if buckets > 0 => {
+                        Ok(Box::new(MetaCommand::Histogram(column, buckets)))
+                    }
+                    _ => Err(Error::SyntaxError(format!(
+                        "Syntax error. Failed to parse 'buckets' from '{}'",
+                        buckets
+                    ))),
+                }
+            }
+            [".peek", size] => {
+                let size = size.parse::<usize>().map_err(|_| {
+                    Error::SyntaxError(format!(
+                        "Syntax error. Failed to parse 'peek' from '{}'",
+                        size
+                    ))
+                })?;
+
+                if size == 0 {
+                    return Err(Error::SyntaxError(
+                        "Syntax error. '.peek' must be greater than 0".to_string(),
+                    ));
+                }
+
+                Ok(Box::new(MetaCommand::Peek(size)))
+            }
+            [".validate", _, ..] => {
+                let command = build_command(&input[".validate".len()..], registry)?;
+
+                let insert = match command.as_any().downcast_ref() {
+                    Some(Statement::Insert(input)) => Some(input.clone()),
+                    _ => None,
+                };
+
+                Ok(Box::new(MetaCommand::Validate(insert)))
+            }
+            [".rowid", id] => {
+                let id = id.parse::<usize>().map_err(|_| {
+                    Error::SyntaxError(format!("Syntax error. Failed to parse 'id' from '{}'", id))
+                })?;
+
+                Ok(Box::new(MetaCommand::RowId(id)))
+            }
+            [".format", "id-width", width] => {
+                let width = width.parse::<usize>().map_err(|_| {
+                    Error::SyntaxError(format!(
+                        "Syntax error. Failed to parse 'id-width' from '{}'",
+                        width
+                    ))
+                })?;
+
+                if width > ID_WIDTH_MAX {
+                    return Err(Error::SyntaxError(format!(
+                        "Syntax error. 'id-width' can't be more than {}",
+                        ID_WIDTH_MAX
+                    )));
+                }
+
+                Ok(Box::new(MetaCommand::IdWidth(width)))
+            }
+            [name, ..] => match META_COMMAND_USAGES.iter().find(|(known, _)| known == name) {
+                Some((_, usage)) => Err(build_usage_error(usage)),
+                None => Err(build_unrecognized_meta_command_error(input)),
+            },
+            [] => Err(build_unrecognized_meta_command_error(input)),
+        }
+    }
+
+    /// The name used to call the meta command.
+    fn name(&self) -> &'static str {
+        match self {
+            MetaCommand::Exit(_) => ".exit",
+            MetaCommand::Memory => ".memory",
+            MetaCommand::Cache(_) => ".cache",
+            MetaCommand::Stats => ".stats",
+            MetaCommand::RowSeparator(_) => ".rowsep",
+            MetaCommand::Mode(_) => ".mode",
+            MetaCommand::IdWidth(_) => ".format",
+            MetaCommand::ReadOnly(_) => ".readonly",
+            MetaCommand::Seed(_) => ".seed",
+            MetaCommand::AllowDomain(_) => ".allowdomain",
+            MetaCommand::Undo => ".undo",
+            MetaCommand::Comment(_) => ".comment",
+            MetaCommand::NumberFormat(_) => ".numberformat",
+            MetaCommand::Dedup(_) => ".dedup",
+            MetaCommand::Escape(_) => ".escape",
+            MetaCommand::Trailer(_) => ".trailer",
+            MetaCommand::Intern(_) => ".intern",
+            MetaCommand::DefaultEmail(_) => ".defaultemail",
+            MetaCommand::Truncate(_) => ".truncate",
+            MetaCommand::MaxOutput(_) => ".maxoutput",
+            MetaCommand::Timeout(_) => ".timeout",
+            MetaCommand::Peek(_) => ".peek",
+            MetaCommand::RowId(_) => ".rowid",
+            MetaCommand::Validate(_) => ".validate",
+            MetaCommand::Histogram(_, _) => ".histogram",
         }
     }
 }
 
 impl Command for MetaCommand {
     /// Executes an different logic for each variant of the `enum`.
-    fn execute(&self, _table: &mut Table) -> Result<String, Error> {
-        match *self {
-            MetaCommand::Exit => process::exit(0),
+    /// The limits set by the embedder can't be changed on a table
+    /// created read-only.
+    fn execute(&self, table: &mut Table, format: &mut Format) -> Result<String, Error> {
+        if table.is_readonly_locked()
+            && matches!(self, MetaCommand::Timeout(_) | MetaCommand::MaxOutput(_))
+        {
+            return Err(Error::ReadOnly(format!(
+                "Read-only mode. '{}' can't be changed on a table created read-only",
+                self.name()
+            )));
+        }
+
+        match self {
+            MetaCommand::Exit(code) => process::exit(*code),
+            MetaCommand::Memory => Ok(format!("{} bytes\n", table.approx_memory_bytes())),
+            MetaCommand::Cache(enabled) => {
+                table.set_cache_enabled(*enabled);
+                Ok("".to_string())
+            }
+            MetaCommand::Stats => {
+                let mut stats = format!("cache hits: {}\n", table.cache_hits());
+
+                if let Some(ratio) = table.intern_dedup_ratio() {
+                    stats.push_str(&format!("intern dedup ratio: {:.2}\n", ratio));
+                }
+
+                Ok(stats)
+            }
+            MetaCommand::RowSeparator(separator) => {
+                format.row_separator = separator.clone();
+                Ok("".to_string())
+            }
+            MetaCommand::Mode(mode) => {
+                format.mode = *mode;
+                Ok("".to_string())
+            }
+            MetaCommand::IdWidth(width) => {
+                format.id_width = *width;
+                Ok("".to_string())
+            }
+            MetaCommand::NumberFormat(number_format) => {
+                format.number_format = *number_format;
+                Ok("".to_string())
+            }
+            MetaCommand::Dedup(dedup) => {
+                format.dedup = *dedup;
+                Ok("".to_string())
+            }
+            MetaCommand::Escape(escape) => {
+                format.escape = *escape;
+                Ok("".to_string())
+            }
+            MetaCommand::Trailer(trailer) => {
+                format.trailer = *trailer;
+                Ok("".to_string())
+            }
+            MetaCommand::Intern(enabled) => {
+                table.set_interning(*enabled);
+                Ok("".to_string())
+            }
+            MetaCommand::DefaultEmail(email) => {
+                table.set_default_email(email)?;
+                Ok("".to_string())
+            }
+            MetaCommand::Truncate(truncate) => {
+                table.set_truncate(*truncate);
+                Ok("".to_string())
+            }
+            MetaCommand::MaxOutput(bytes) => {
+                format.max_output = *bytes;
+                Ok("".to_string())
+            }
+            MetaCommand::Timeout(milliseconds) => {
+                let timeout = match milliseconds {
+                    0 => None,
+                    _ => Some(Duration::from_millis(*milliseconds)),
+                };
+
+                table.set_timeout(timeout);
+                Ok("".to_string())
+            }
+            MetaCommand::Peek(size) => Ok(peek(*size, table, format)),
+            MetaCommand::Validate(insert) => {
+                if let Some(input) = insert {
+                    parse_insert(input, table.parse_options())?;
+                }
+
+                Ok("OK\n".to_string())
+            }
+            MetaCommand::RowId(id) => match table.find_row(*id) {
+                Some(row) => Ok(format!("{}\n", row.rowid())),
+                None => Err(Error::SyntaxError(format!(
+                    "Syntax error. There's no row with id {}",
+                    id
+                ))),
+            },
+            MetaCommand::Histogram(column, buckets) => Ok(histogram(*column, *buckets, table)),
+            MetaCommand::ReadOnly(readonly) => {
+                table.set_readonly(*readonly)?;
+                Ok("".to_string())
+            }
+            MetaCommand::Seed(seed) => {
+                table.set_seed(*seed);
+                Ok("".to_string())
+            }
+            MetaCommand::AllowDomain(domain) => {
+                table.allow_domain(domain);
+                Ok("".to_string())
+            }
+            MetaCommand::Undo => {
+                table.undo()?;
+                Ok("".to_string())
+            }
+            MetaCommand::Comment(Some(text)) => {
+                table.set_description(text.clone());
+                Ok("".to_string())
+            }
+            MetaCommand::Comment(None) => Ok(table
+                .description()
+                .map(|description| format!("{}\n", description))
+                .unwrap_or_default()),
+        }
+    }
+
+    fn command_kind(&self) -> CommandKind {
+        CommandKind::Meta(self.name())
+    }
+
+    /// `.comment` is mutating only when it sets the description.
+    fn is_mutating(&self) -> bool {
+        matches!(self, MetaCommand::Comment(Some(_))) || self.command_kind().is_mutating()
+    }
+
+    fn exit_code(&self) -> Option<i32> {
+        match self {
+            MetaCommand::Exit(code) => Some(*code),
+            _ => None,
         }
     }
 }
 
+/// Formats the first and last `size` `Row`s of the `table` in `id` order,
+/// with a `...` line between them. If there aren't more than `2 * size`
+/// `Row`s, all of them are shown without it.
+fn peek(size: usize, table: &Table, format: &Format) -> String {
+    let mut rows: Vec<&Row> = table.list_rows().iter().collect();
+    rows.sort_by_key(|row| row.id());
+
+    let format_rows =
+        |rows: &[&Row]| -> Vec<String> { rows.iter().map(|row| format.format_row(row)).collect() };
+
+    if rows.len() <= size.saturating_mul(2) {
+        return format.join_lines(format_rows(&rows));
+    }
+
+    let mut lines = format_rows(&rows[..size]);
+    lines.push("...".to_string());
+    lines.extend(format_rows(&rows[rows.len() - size..]));
+
+    format.join_lines(lines)
+}
+
+/// Splits the values of `column`, from the smallest to the greatest, into
+/// `buckets` ranges of the same size, writing one bar per range like
+/// `1-5 | ##### 5`. There are less ranges if there aren't enough values
+/// for all of them, and nothing at all if the `table` is empty. The ranges
+/// and the bars are padded, so that the counts, even `0`, line up.
+fn histogram(column: Column, buckets: usize, table: &Table) -> String {
+    let values: Vec<usize> = table
+        .list_rows()
+        .iter()
+        .filter_map(|row| row.get_number(column))
+        .collect();
+
+    let (min, max) = match (values.iter().min(), values.iter().max()) {
+        (Some(&min), Some(&max)) => (min as u128, max as u128),
+        _ => return "".to_string(),
+    };
+
+    let span = max - min + 1;
+    let buckets = span.min(buckets as u128);
+    let mut counts = vec![0; buckets as usize];
+
+    for value in values {
+        counts[((value as u128 - min) * buckets / span) as usize] += 1;
+    }
+
+    let ranges: Vec<String> = (0..buckets)
+        .map(|bucket| {
+            let start = min + bucket * span / buckets;
+            let end = min + (bucket + 1) * span / buckets - 1;
+
+            format!("{}-{}", start, end)
+        })
+        .collect();
+
+    let range_width = ranges.iter().map(String::len).max().unwrap_or(0);
+    let bar_width = counts.iter().copied().max().unwrap_or(0);
+
+    ranges
+        .iter()
+        .zip(&counts)
+        .map(|(range, count)| {
+            format!(
+                "{:<range_width$} | {:<bar_width$} {}\n",
+                range,
+                "#".repeat(*count),
+                count,
+                range_width = range_width,
+                bar_width = bar_width
+            )
+        })
+        .collect()
+}
+
 impl AsAny for MetaCommand {
     fn as_any(&self) -> &dyn Any {
         self
@@ -86,7 +790,20 @@ impl AsAny for MetaCommand {
 #[derive(Debug, PartialEq)]
 enum Statement {
     Insert(String),
-    Select,
+    /// The normalized input, used as the cache key, and its parsed `Query`.
+    Select(String, Query),
+    /// `select pages pagesize N`, the page size is never `0`.
+    SelectPages(usize),
+    /// `select sample N`, up to `N` pseudo random `Row`s.
+    SelectSample(usize),
+    /// `select since K`, the `Row`s inserted after the one with the `rowid` `K`.
+    SelectSince(usize),
+    /// `update id <from> to <to>`, changes the `id` of a `Row`.
+    UpdateId(usize, usize),
+    /// `delete where <filter>`, removes the `Row`s that match it.
+    Delete(Filter),
+    /// A lone `;`, it does nothing.
+    Empty,
 }
 
 impl Statement {
@@ -95,15 +812,103 @@ impl Statement {
     ///
     /// All of the possibilities are just the variants on the `enum`.
     fn from_str(input: &str) -> Result<Box<dyn Command>, Error> {
-        let input = input.to_string();
+        let mut tokens = Lexer::new(input);
+        let keyword = tokens.next().map(|token| token.text);
+        let next_keyword = tokens.peek().map(|token| token.text);
 
-        if input.starts_with("insert") {
-            Ok(Box::new(Statement::Insert(input)))
-        } else if input.starts_with("select") {
-            Ok(Box::new(Statement::Select))
-        } else {
-            Err(build_not_implemented_error(&input))
+        match keyword {
+            Some("insert") => Ok(Box::new(Statement::Insert(input.to_string()))),
+            Some("select") if next_keyword == Some("pages") => {
+                tokens.next();
+                Ok(Box::new(Statement::parse_select_pages(&mut tokens)?))
+            }
+            Some("select") if next_keyword == Some("since") => {
+                tokens.next();
+                let rowid = tokens.next_number("since")?;
+                tokens.expect_end()?;
+
+                Ok(Box::new(Statement::SelectSince(rowid)))
+            }
+            Some("select") if next_keyword == Some("sample") => {
+                tokens.next();
+                let size = tokens.next_number("sample")?;
+                tokens.expect_end()?;
+
+                Ok(Box::new(Statement::SelectSample(size)))
+            }
+            Some("select") => {
+                let query = input.parse::<Query>()?;
+                let normalized_input = input.split_whitespace().collect::<Vec<_>>().join(" ");
+
+                Ok(Box::new(Statement::Select(normalized_input, query)))
+            }
+            Some("update") => {
+                expect_keywords(&mut tokens, &["id"])?;
+                let from = tokens.next_number("id")?;
+                expect_keywords(&mut tokens, &["to"])?;
+                let to = tokens.next_number("id")?;
+                tokens.expect_end()?;
+
+                Ok(Box::new(Statement::UpdateId(from, to)))
+            }
+            Some("delete") => {
+                expect_keywords(&mut tokens, &["where"])?;
+                let filter = Filter::parse(&mut tokens)?;
+                tokens.expect_end()?;
+
+                Ok(Box::new(Statement::Delete(filter)))
+            }
+            _ => Err(build_not_implemented_error(input)),
+        }
+    }
+
+    /// Parses what comes after `select pages`, like `pagesize 50`.
+    fn parse_select_pages(tokens: &mut Lexer) -> Result<Self, Error> {
+        expect_keywords(tokens, &["pagesize"])?;
+
+        let position = tokens.next_position();
+        let page_size = tokens.next_number("pagesize")?;
+
+        if page_size == 0 {
+            return Err(at_position(
+                Error::SyntaxError("Syntax error. 'pagesize' must be greater than 0".to_string()),
+                position,
+            ));
         }
+
+        tokens.expect_end()?;
+
+        Ok(Statement::SelectPages(page_size))
+    }
+
+    /// Returns how many pages of `page_size` `Row`s the `table` has.
+    /// This is what get's called when something like
+    /// `Statement::SelectPages(50).execute()` happens.
+    fn select_pages(&self, page_size: usize, table: &Table) -> Result<String, Error> {
+        let pages = table.count().div_ceil(page_size);
+
+        Ok(format!("{}\n", pages))
+    }
+
+    /// Returns up to `size` `Row`s picked by the `Random` generator of the `table`,
+    /// these aren't cached since every sample moves the generator forward.
+    fn select_sample(&self, size: usize, table: &Table, format: &Format) -> Result<String, Error> {
+        let lines = table
+            .sample_rows(size)
+            .into_iter()
+            .map(|index| format.format_row(&table.list_rows()[index]));
+
+        Ok(format.join_lines(lines))
+    }
+
+    /// Returns the `Row`s with a `rowid` greater than `rowid`, for incremental syncs.
+    fn select_since(&self, rowid: usize, table: &Table, format: &Format) -> Result<String, Error> {
+        let lines = table
+            .rows_since(rowid)
+            .iter()
+            .map(|row| format.format_row(row));
+
+        Ok(format.join_lines(lines))
     }
 
     /// Creates a new `Row` based of an `input` `&str` and inserts it
@@ -111,39 +916,325 @@ impl Statement {
     /// This is what get's called when something like
     /// `Statement::Insert("insert 1 john john@mailbox.com").execute()` happens.
     fn insert(&self, input: &str, table: &mut Table) -> Result<String, Error> {
-        let row = Row::from_str(&input)?;
+        match parse_insert(input, table.parse_options())? {
+            Insert::Row(row) => {
+                table.validate_row(&row)?;
+                table.add_row(row);
+
+                Ok("".to_string())
+            }
+            Insert::IfNotExists(row) => self.insert_if_not_exists(row, table),
+            Insert::Into(rows) => self.insert_into(rows, table),
+        }
+    }
+
+    /// With `insert if not exists`, the `Row` is only inserted if its `id`
+    /// is free, otherwise the existing `Row` is returned without an error.
+    fn insert_if_not_exists(&self, row: Row, table: &mut Table) -> Result<String, Error> {
+        if let Some(existing_row) = table.find_row(row.id()) {
+            return Ok(format!("Exists: {}\n", existing_row));
+        }
+
+        table.validate_row(&row)?;
 
+        let output = format!("Created: {}\n", row);
         table.add_row(row);
 
+        Ok(output)
+    }
+
+    /// With `insert into users values (1, john, john@mailbox.com), (...)`,
+    /// every tuple is validated before any of them is inserted,
+    /// so either all of the `Row`s are inserted or none of them.
+    fn insert_into(&self, rows: Vec<Row>, table: &mut Table) -> Result<String, Error> {
+        for row in &rows {
+            table.validate_row(row)?;
+        }
+
+        table.add_rows(rows);
+
         Ok("".to_string())
     }
 
-    /// Returns all `Row`s inside of a table as String.
+    /// Returns all `Row`s inside of a table as String, sorted
+    /// if the `query` has an `order by` clause and bucketed
+    /// if it has a `group by` one. Without `order by`, the `Row`s
+    /// are always in `rowid` order, the order they were inserted.
     /// This is what get's called when something like
-    /// `Statement::Select.execute()` happens.
-    fn select(&self, table: &Table) -> Result<String, Error> {
-        let rows = table.list_rows();
+    /// `Statement::Select(input, query).execute()` happens.
+    ///
+    /// If the `select` cache is on, the result is reused for identical inputs
+    /// written with the same `format`.
+    fn select(
+        &self,
+        input: &str,
+        query: &Query,
+        table: &Table,
+        format: &Format,
+    ) -> Result<String, Error> {
+        if let Some(result) = table.cached_select(input, format) {
+            return Ok(result);
+        }
+
+        let result = self.query(query, table, format)?;
+
+        table.cache_select(input.to_string(), format, result.clone());
+
+        Ok(result)
+    }
+
+    /// Runs the `query` over the `Row`s of the `table` and formats the result.
+    fn query(&self, query: &Query, table: &Table, format: &Format) -> Result<String, Error> {
+        Ok(format.join_lines(select_lines(table, format, query)?))
+    }
+}
+
+/// Runs the `query` over the `Row`s of the `table`, yielding one formatted line
+/// per `Row`, or per group with `group by`, the same ones a `select` returns.
+///
+/// A plain `select [limit N]` formats the `Row`s lazily, as they are pulled,
+/// the other ones need to see every `Row` first. Errors, like an id arithmetic
+/// out of range, are returned before any line is yielded.
+///
+/// Here's an example:
+/// ```
+/// use scoolite::command::select_lines;
+/// use scoolite::format::Format;
+/// use scoolite::query::Query;
+/// use scoolite::table::{Table, TableExt};
+///
+/// let mut table = Table::new();
+/// table.insert_values(1, "john", "john@mailbox.com").unwrap();
+/// table.insert_values(2, "mary", "mary@mailbox.com").unwrap();
+///
+/// let query = "select limit 1".parse::<Query>().unwrap();
+/// let format = Format::new();
+/// let lines: Vec<String> = select_lines(&table, &format, &query).unwrap().collect();
+///
+/// assert_eq!(lines, vec!["(1, john, john@mailbox.com)"]);
+/// ```
+pub fn select_lines<'a>(
+    table: &'a Table,
+    format: &'a Format,
+    query: &'a Query,
+) -> Result<Box<dyn Iterator<Item = String> + 'a>, Error> {
+    let format_row = move |row: &Row| {
+        if query.ids {
+            row.id().to_string()
+        } else {
+            format.format_row(row)
+        }
+    };
+
+    if query.is_count()
+        && query.filter.is_none()
+        && query.limit.is_none()
+        && query.offset.is_none()
+        && !format.dedup
+    {
+        return Ok(Box::new(iter::once(format!("({})", table.count()))));
+    }
+
+    // A filter can scan the whole table even with a limit, so with
+    // a timeout it goes through the loop below, which checks it.
+    if query.group_by.is_none()
+        && query.projection.is_empty()
+        && query.order_by.is_none()
+        && !format.dedup
+        && (query.filter.is_none() || table.timeout().is_none())
+    {
+        return Ok(Box::new(
+            table
+                .list_rows()
+                .iter()
+                .filter(move |row| query.matches(row))
+                .skip(query.offset.unwrap_or(0))
+                .take(query.limit.unwrap_or(usize::MAX))
+                .map(format_row),
+        ));
+    }
+
+    let mut rows: Vec<&Row> = vec![];
+
+    for row in table.list_rows() {
+        table.check_deadline()?;
+
+        if query.matches(row) {
+            rows.push(row);
+        }
+    }
+
+    if format.dedup {
+        rows = dedup_rows(rows);
+    }
+
+    if query.is_count() {
+        let count = rows
+            .len()
+            .saturating_sub(query.offset.unwrap_or(0))
+            .min(query.limit.unwrap_or(usize::MAX));
+
+        return Ok(Box::new(iter::once(format!("({})", count))));
+    }
+
+    let lines: Box<dyn Iterator<Item = String> + 'a> = match query.group_by {
+        Some(group_by) => {
+            if let Some(order_by) = &query.order_by {
+                rows.sort_by(|a, b| order_by.compare(a, b));
+            }
+
+            let mut groups = query.group(group_by, &rows)?;
+
+            groups.drain(..query.offset.unwrap_or(0).min(groups.len()));
+
+            if let Some(limit) = query.limit {
+                groups.truncate(limit);
+            }
+
+            Box::new(groups.into_iter())
+        }
+        None if !query.projection.is_empty() => Box::new(
+            query
+                .order_and_limit(rows)
+                .into_iter()
+                .map(|r| query.project(r, 1))
+                .collect::<Result<Vec<String>, Error>>()?
+                .into_iter(),
+        ),
+        None => Box::new(query.order_and_limit(rows).into_iter().map(format_row)),
+    };
+
+    Ok(lines)
+}
+
+/// The `Row`s of each form of `insert`, see `parse_insert`.
+enum Insert {
+    /// `insert 1 john john@mailbox.com`
+    Row(Row),
+    /// `insert if not exists 1 john john@mailbox.com`
+    IfNotExists(Row),
+    /// `insert into users values (1, john, john@mailbox.com), (...)`
+    Into(Vec<Row>),
+}
+
+/// Parses the `Row`s of an `insert` with the `options` of the `Table`,
+/// without checking them against its validators.
+fn parse_insert(input: &str, options: ParseOptions) -> Result<Insert, Error> {
+    let mut tokens = Lexer::new(input);
+    tokens.next();
+
+    match tokens.peek().map(|token| token.text) {
+        Some("if") => {
+            expect_keywords(&mut tokens, &["if", "not", "exists"])?;
+
+            Ok(Insert::IfNotExists(Row::parse_with_options(
+                &mut tokens,
+                options,
+            )?))
+        }
+        Some("into") => {
+            expect_keywords(&mut tokens, &["into", TABLE_NAME, "values"])?;
+
+            let mut rows = vec![Row::parse_tuple(&mut tokens, options)?];
+
+            while tokens.peek().map(|token| token.kind) == Some(TokenKind::Comma) {
+                tokens.next();
+                rows.push(Row::parse_tuple(&mut tokens, options)?);
+            }
+
+            tokens.expect_end()?;
 
-        Ok(rows.iter().map(|r| format!("{}\n", r)).collect())
+            Ok(Insert::Into(rows))
+        }
+        _ => Ok(Insert::Row(Row::parse_with_options(&mut tokens, options)?)),
     }
 }
 
+/// Keeps only the last `Row` inserted for each `id`, in the position of that `Row`.
+fn dedup_rows(rows: Vec<&Row>) -> Vec<&Row> {
+    let mut seen_ids = HashSet::new();
+
+    let mut rows: Vec<&Row> = rows
+        .into_iter()
+        .rev()
+        .filter(|row| seen_ids.insert(row.id()))
+        .collect();
+    rows.reverse();
+
+    rows
+}
+
 impl Command for Statement {
     /// Executes an different logic for each variant of the `enum`.
     /// If it succeeds, it will return the String of the command executed
-    /// concatenated with `Executed.\n`.
-    fn execute(&self, table: &mut Table) -> Result<String, Error> {
+    /// concatenated with `Executed.\n`, unless the trailer is turned off.
+    fn execute(&self, table: &mut Table, format: &mut Format) -> Result<String, Error> {
+        if let Some(result) = self.execute_shared(table, format) {
+            return result;
+        }
+
+        let result = match self {
+            Statement::Insert(input) => self.insert(input, table),
+            Statement::UpdateId(from, to) => {
+                if let Some(row) = table.find_row(*from) {
+                    table.validate_row(&Row::new(*to, row.username(), row.email())?)?;
+                }
+
+                table.rekey_row(*from, *to)?;
+
+                Ok("".to_string())
+            }
+            Statement::Delete(filter) => {
+                let count = table.delete_rows(|row| filter.matches(row));
+
+                Ok(format!("{} rows deleted\n", count))
+            }
+            Statement::Empty
+            | Statement::Select(_, _)
+            | Statement::SelectPages(_)
+            | Statement::SelectSample(_)
+            | Statement::SelectSince(_) => unreachable!("it only reads the table"),
+        };
+
+        with_trailer(result, format)
+    }
+
+    /// The `select`s, and the empty statement, only read the `table`.
+    fn execute_shared(&self, table: &Table, format: &mut Format) -> Option<Result<String, Error>> {
         let result = match self {
-            Statement::Insert(input) => self.insert(&input, table),
-            Statement::Select => self.select(table),
+            Statement::Empty => return Some(Ok("".to_string())),
+            Statement::Select(input, query) => self.select(input, query, table, format),
+            Statement::SelectPages(page_size) => self.select_pages(*page_size, table),
+            Statement::SelectSample(size) => self.select_sample(*size, table, format),
+            Statement::SelectSince(rowid) => self.select_since(*rowid, table, format),
+            Statement::Insert(_) | Statement::UpdateId(_, _) | Statement::Delete(_) => return None,
         };
 
-        if result.is_ok() {
-            return result.map(|s| format!("{}Executed.\n", s));
+        Some(with_trailer(result, format))
+    }
+
+    fn command_kind(&self) -> CommandKind {
+        match self {
+            Statement::Insert(_) => CommandKind::Insert,
+            Statement::Select(_, _)
+            | Statement::SelectPages(_)
+            | Statement::SelectSample(_)
+            | Statement::SelectSince(_) => CommandKind::Select,
+            Statement::Empty => CommandKind::Empty,
+            Statement::UpdateId(_, _) => CommandKind::Update,
+            Statement::Delete(_) => CommandKind::Delete,
         }
+    }
+}
 
-        result
+/// Concatenates `Executed.\n` to the output of a statement that
+/// succeeded, unless the trailer is turned off.
+fn with_trailer(result: Result<String, Error>, format: &Format) -> Result<String, Error> {
+    if result.is_ok() && format.trailer {
+        return result.map(|s| format!("{}Executed.\n", s));
     }
+
+    result
 }
 
 impl AsAny for Statement {
@@ -152,29 +1243,136 @@ impl AsAny for Statement {
     }
 }
 
+/// The function that executes a custom command, it receives the
+/// whole input and the `Table`, just like `Command::execute`.
+pub type CommandHandler = dyn Fn(&str, &mut Table) -> Result<String, Error>;
+
+/// `CommandRegistry` holds custom commands, so that embedders can add
+/// their own statements without forking `scoolite`.
+///
+/// Here's an example:
+/// ```
+/// use scoolite::command::CommandRegistry;
+/// use scoolite::table::Table;
+///
+/// let mut registry = CommandRegistry::new();
+/// registry.register_command("ping", |_input, _table| Ok("pong".to_string()));
+///
+/// let mut table = Table::new();
+/// let output = registry.run_command(&mut table, "ping".to_string());
+///
+/// assert_eq!(output, Ok("pong".to_string()));
+/// ```
+#[derive(Default)]
+pub struct CommandRegistry {
+    commands: Vec<(String, Rc<CommandHandler>)>,
+}
+
+impl CommandRegistry {
+    /// Creates a registry without any custom command.
+    pub fn new() -> Self {
+        CommandRegistry { commands: vec![] }
+    }
+
+    /// Registers a `handler` that gets called for every input that starts
+    /// with `prefix`. Custom commands are matched before the built-in ones,
+    /// in the order they were registered.
+    pub fn register_command<F>(&mut self, prefix: &str, handler: F)
+    where
+        F: Fn(&str, &mut Table) -> Result<String, Error> + 'static,
+    {
+        self.commands.push((prefix.to_string(), Rc::new(handler)));
+    }
+
+    /// Does the same as `run_command`, but using the custom commands
+    /// of this registry too.
+    pub fn run_command(&self, table: &mut Table, command: String) -> Result<String, Error> {
+        self.run_command_with_format(table, &mut Format::new(), command)
+    }
+
+    /// Does the same as `run_command_with_format`, but using the custom
+    /// commands of this registry too.
+    pub fn run_command_with_format(
+        &self,
+        table: &mut Table,
+        format: &mut Format,
+        command: String,
+    ) -> Result<String, Error> {
+        let command_result = build_command(&command, self);
+
+        try_execute_command(command_result, table, format)
+    }
+
+    /// Returns the first custom command whose prefix matches the `input`.
+    fn find(&self, input: &str) -> Option<CustomCommand> {
+        self.commands
+            .iter()
+            .find(|(prefix, _)| input.starts_with(prefix.as_str()))
+            .map(|(_, handler)| CustomCommand {
+                input: input.to_string(),
+                handler: Rc::clone(handler),
+            })
+    }
+}
+
+/// `CustomCommand` is a `Command` registered on a `CommandRegistry`.
+struct CustomCommand {
+    input: String,
+    handler: Rc<CommandHandler>,
+}
+
+impl Command for CustomCommand {
+    /// Just calls the registered handler with the `input`.
+    fn execute(&self, table: &mut Table, _format: &mut Format) -> Result<String, Error> {
+        (self.handler)(&self.input, table)
+    }
+
+    fn command_kind(&self) -> CommandKind {
+        CommandKind::Custom
+    }
+}
+
+impl AsAny for CustomCommand {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use crate::command::{build_command, run_command, MetaCommand, Statement};
+    use std::env;
+    use std::fs;
+    use std::thread;
+    use std::time::Duration;
+
+    use crate::command::{
+        build_command, execute_script, run_command, run_command_with_format, run_script_file,
+        select_lines, CommandKind, CommandOutput, CommandRegistry, CustomCommand, MetaCommand,
+        Statement,
+    };
     use crate::error::Error;
-    use crate::table::Table;
+    use crate::format::{Format, Mode};
+    use crate::query::{OrderBy, Projection, Query};
+    use crate::row::{Column, Row};
+    use crate::table::{Table, TableExt};
 
     #[test]
     fn build_command_meta_command() {
         let input = ".exit".to_string();
 
-        let command = build_command(&input).unwrap();
+        let command = build_command(&input, &CommandRegistry::new()).unwrap();
 
         // stupid necessary casting, because command is a Command trait object
         let command = command.as_any().downcast_ref::<MetaCommand>().unwrap();
 
-        assert_eq!(*command, MetaCommand::Exit);
+        assert_eq!(*command, MetaCommand::Exit(0));
     }
 
     #[test]
     fn build_command_statement() {
         let input = "insert a b c".to_string();
 
-        let command = build_command(&input).unwrap();
+        let command = build_command(&input, &CommandRegistry::new()).unwrap();
 
         // stupid necessary casting, because command is a Command trait object
         let command = command.as_any().downcast_ref::<Statement>().unwrap();
@@ -183,22 +1381,60 @@ mod test {
     }
 
     #[test]
-    fn statement_from_str_insert() {
-        let input = "insert a b c";
-
-        let insert_statement = Statement::from_str(input).unwrap();
+    fn build_command_meta_command_leading_whitespace() {
+        let command = build_command("  .exit", &CommandRegistry::new()).unwrap();
 
-        let insert_statement = insert_statement
-            .as_any()
-            .downcast_ref::<Statement>()
-            .unwrap();
+        let command = command.as_any().downcast_ref::<MetaCommand>().unwrap();
 
-        assert_eq!(*insert_statement, Statement::Insert(input.to_string()));
+        assert_eq!(*command, MetaCommand::Exit(0));
     }
 
     #[test]
-    fn statement_from_str_select() {
-        let input = "select";
+    fn build_command_statement_leading_whitespace() {
+        let command = build_command("  select", &CommandRegistry::new()).unwrap();
+
+        let command = command.as_any().downcast_ref::<Statement>().unwrap();
+
+        assert_eq!(
+            *command,
+            Statement::Select("select".to_string(), Query::default())
+        );
+    }
+
+    #[test]
+    fn statement_from_str_insert() {
+        let input = "insert a b c";
+
+        let insert_statement = Statement::from_str(input).unwrap();
+
+        let insert_statement = insert_statement
+            .as_any()
+            .downcast_ref::<Statement>()
+            .unwrap();
+
+        assert_eq!(*insert_statement, Statement::Insert(input.to_string()));
+    }
+
+    #[test]
+    fn statement_from_str_select() {
+        let input = "select";
+
+        let select_statement = Statement::from_str(input).unwrap();
+
+        let select_statement = select_statement
+            .as_any()
+            .downcast_ref::<Statement>()
+            .unwrap();
+
+        assert_eq!(
+            *select_statement,
+            Statement::Select(input.to_string(), Query::default())
+        );
+    }
+
+    #[test]
+    fn statement_from_str_select_order_by() {
+        let input = "select order by username collate nocase";
 
         let select_statement = Statement::from_str(input).unwrap();
 
@@ -207,7 +1443,29 @@ mod test {
             .downcast_ref::<Statement>()
             .unwrap();
 
-        assert_eq!(*select_statement, Statement::Select);
+        let expected_query = Query {
+            order_by: Some(OrderBy {
+                column: Column::Username,
+                nocase: true,
+                descending: false,
+            }),
+            ..Query::default()
+        };
+
+        assert_eq!(
+            *select_statement,
+            Statement::Select(input.to_string(), expected_query)
+        );
+    }
+
+    #[test]
+    fn statement_from_str_select_order_by_unknown_column() {
+        let error = Statement::from_str("select order by age").err().unwrap();
+
+        assert_eq!(
+            error,
+            Error::SyntaxError("Syntax error. Unknown column 'age' at position 16".to_string())
+        );
     }
 
     #[test]
@@ -254,7 +1512,23 @@ mod test {
 
         assert_eq!(
             error,
-            Error::SyntaxError("Syntax error. Failed to parse 'id' of input".to_string())
+            Error::SyntaxError(
+                "Syntax error. Failed to parse 'id' from 'text_id' at position 7".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn run_command_insert_missing_email() {
+        let mut table = Table::new();
+
+        let error = run_command(&mut table, "insert 1 otaviopace".to_string()).unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::SyntaxError(
+                "Syntax error. Failed to parse 'email' from '' at position 19".to_string()
+            )
         );
     }
 
@@ -270,7 +1544,2233 @@ mod test {
 
         assert_eq!(
             error,
-            Error::SyntaxError("Syntax error. Failed to parse 'id' of input".to_string())
+            Error::SyntaxError(
+                "Syntax error. Failed to parse 'id' from '-1' at position 7".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn run_command_insert_leading_plus_and_spaces() {
+        let mut table = Table::new();
+
+        let error = run_command(&mut table, "insert +1 a a@b.com".to_string()).unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::SyntaxError(
+                "Syntax error. Failed to parse 'id' from '+1' at position 7".to_string()
+            )
+        );
+
+        let error = run_command(&mut table, "select limit +1".to_string()).unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::SyntaxError(
+                "Syntax error. Failed to parse 'limit' from '+1' at position 13".to_string()
+            )
+        );
+
+        let output = run_command(&mut table, "insert   1   a a@b.com ".to_string()).unwrap();
+
+        assert_eq!(output, "Executed.\n");
+        assert_eq!(table.find_row(1).unwrap().username(), "a");
+
+        let error = run_command(&mut table, "insert 1 2 a a@b.com".to_string()).unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::SyntaxError("Syntax error. Unexpected 'a@b.com' at position 13".to_string())
+        );
+    }
+
+    fn insert_users(table: &mut Table, ids: impl IntoIterator<Item = usize>) {
+        for id in ids {
+            table
+                .insert_values(id, &format!("user{}", id), "user@mail.com")
+                .unwrap();
+        }
+    }
+
+    fn insert_mixed_case_usernames(table: &mut Table) {
+        for input in &[
+            "insert 1 bob bob@mail.com",
+            "insert 2 Carol carol@mail.com",
+            "insert 3 alice alice@mail.com",
+            "insert 4 Dave dave@mail.com",
+        ] {
+            run_command(table, input.to_string()).unwrap();
+        }
+    }
+
+    #[test]
+    fn run_command_select_order_by_username() {
+        let mut table = Table::new();
+
+        insert_mixed_case_usernames(&mut table);
+
+        let output = run_command(&mut table, "select order by username".to_string()).unwrap();
+
+        assert_eq!(
+            output,
+            "(2, Carol, carol@mail.com)\n\
+             (4, Dave, dave@mail.com)\n\
+             (3, alice, alice@mail.com)\n\
+             (1, bob, bob@mail.com)\n\
+             Executed.\n"
+        );
+    }
+
+    #[test]
+    fn run_command_select_order_by_username_nocase() {
+        let mut table = Table::new();
+
+        insert_mixed_case_usernames(&mut table);
+
+        let output = run_command(
+            &mut table,
+            "select order by username collate nocase".to_string(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            output,
+            "(3, alice, alice@mail.com)\n\
+             (1, bob, bob@mail.com)\n\
+             (2, Carol, carol@mail.com)\n\
+             (4, Dave, dave@mail.com)\n\
+             Executed.\n"
+        );
+    }
+
+    #[test]
+    fn statement_from_str_select_group_by() {
+        let input = "select username, count group by username";
+
+        let select_statement = Statement::from_str(input).unwrap();
+
+        let select_statement = select_statement
+            .as_any()
+            .downcast_ref::<Statement>()
+            .unwrap();
+
+        let expected_query = Query {
+            projection: vec![Projection::Column(Column::Username), Projection::Count],
+            group_by: Some(Column::Username),
+            ..Query::default()
+        };
+
+        assert_eq!(
+            *select_statement,
+            Statement::Select(input.to_string(), expected_query)
+        );
+    }
+
+    #[test]
+    fn statement_from_str_select_group_by_ungrouped_column() {
+        let error = Statement::from_str("select email, count group by username")
+            .err()
+            .unwrap();
+
+        assert_eq!(
+            error,
+            Error::SyntaxError(
+                "Syntax error. Column 'email' must appear in 'group by'".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn run_command_select_group_by_count() {
+        let mut table = Table::new();
+
+        for input in &[
+            "insert 1 bob bob@mail.com",
+            "insert 2 alice alice@mail.com",
+            "insert 3 bob bob@other.com",
+            "insert 4 carol carol@mail.com",
+            "insert 5 bob bob@mail.com",
+        ] {
+            run_command(&mut table, input.to_string()).unwrap();
+        }
+
+        let output = run_command(
+            &mut table,
+            "select username, count group by username".to_string(),
+        )
+        .unwrap();
+
+        assert_eq!(output, "(bob, 3)\n(alice, 1)\n(carol, 1)\nExecuted.\n");
+    }
+
+    #[test]
+    fn build_command_custom_command() {
+        let mut registry = CommandRegistry::new();
+        registry.register_command("ping", |_input, _table| Ok("pong".to_string()));
+
+        let command = build_command("ping", &registry).unwrap();
+
+        assert!(command.as_any().downcast_ref::<CustomCommand>().is_some());
+    }
+
+    #[test]
+    fn registry_run_command_custom_command() {
+        let mut registry = CommandRegistry::new();
+        registry.register_command("ping", |_input, _table| Ok("pong".to_string()));
+
+        let mut table = Table::new();
+
+        let output = registry.run_command(&mut table, "ping\n".to_string());
+
+        assert_eq!(output, Ok("pong".to_string()));
+
+        let output = registry
+            .run_command(
+                &mut table,
+                "insert 1 otaviopace otavio@gmail.com".to_string(),
+            )
+            .unwrap();
+
+        assert_eq!(output, "Executed.\n");
+    }
+
+    fn parse_memory_output(output: &str) -> usize {
+        output.trim_end_matches(" bytes\n").parse().unwrap()
+    }
+
+    #[test]
+    fn run_command_memory_grows_with_rows() {
+        let mut table = Table::new();
+
+        let empty = parse_memory_output(&run_command(&mut table, ".memory".to_string()).unwrap());
+
+        run_command(&mut table, "insert 1 john john@mailbox.com".to_string()).unwrap();
+
+        let one_row = parse_memory_output(&run_command(&mut table, ".memory".to_string()).unwrap());
+
+        run_command(
+            &mut table,
+            "insert 2 a_much_longer_username a_much_longer_email@mailbox.com".to_string(),
+        )
+        .unwrap();
+
+        let two_rows =
+            parse_memory_output(&run_command(&mut table, ".memory".to_string()).unwrap());
+
+        assert!(empty < one_row);
+        assert!(one_row < two_rows);
+    }
+
+    #[test]
+    fn run_command_select_cache_hit_and_invalidation() {
+        let mut table = Table::new();
+
+        run_command(&mut table, ".cache on".to_string()).unwrap();
+        run_command(&mut table, "insert 1 john john@mailbox.com".to_string()).unwrap();
+
+        let first = run_command(&mut table, "select".to_string()).unwrap();
+        let second = run_command(&mut table, "  select ".to_string()).unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(
+            run_command(&mut table, ".stats".to_string()),
+            Ok("cache hits: 1\n".to_string())
+        );
+
+        run_command(&mut table, "insert 2 jane jane@mailbox.com".to_string()).unwrap();
+
+        let output = run_command(&mut table, "select".to_string()).unwrap();
+
+        assert_eq!(
+            output,
+            "(1, john, john@mailbox.com)\n(2, jane, jane@mailbox.com)\nExecuted.\n"
+        );
+        assert_eq!(
+            run_command(&mut table, ".stats".to_string()),
+            Ok("cache hits: 1\n".to_string())
+        );
+    }
+
+    #[test]
+    fn run_command_select_cache_is_kept_per_format() {
+        let mut table = Table::new();
+        let mut format = Format::new();
+
+        run_command(&mut table, ".cache on".to_string()).unwrap();
+        run_command(&mut table, "insert 1 john john@mailbox.com".to_string()).unwrap();
+        run_command(&mut table, "select".to_string()).unwrap();
+        run_command_with_format(&mut table, &mut format, ".mode insert".to_string()).unwrap();
+
+        assert_eq!(
+            run_command_with_format(&mut table, &mut format, "select".to_string()),
+            Ok("insert 1 john john@mailbox.com\nExecuted.\n".to_string())
+        );
+        assert_eq!(
+            run_command(&mut table, "select".to_string()),
+            Ok("(1, john, john@mailbox.com)\nExecuted.\n".to_string())
+        );
+        assert_eq!(
+            run_command(&mut table, ".stats".to_string()),
+            Ok("cache hits: 1\n".to_string())
+        );
+    }
+
+    #[test]
+    fn run_command_select_cache_off() {
+        let mut table = Table::new();
+
+        run_command(&mut table, "select".to_string()).unwrap();
+        run_command(&mut table, "select".to_string()).unwrap();
+
+        assert_eq!(
+            run_command(&mut table, ".stats".to_string()),
+            Ok("cache hits: 0\n".to_string())
+        );
+    }
+
+    #[test]
+    fn row_new_too_long_username_same_error_as_insert() {
+        let username = "a".repeat(33);
+
+        let new_error = Row::new(1, &username, "a@mailbox.com").err().unwrap();
+
+        let mut table = Table::new();
+
+        let insert_error =
+            run_command(&mut table, format!("insert 1 {} a@mailbox.com", username)).unwrap_err();
+
+        assert_eq!(
+            new_error,
+            Error::SyntaxError("Syntax error. 'username' is longer than 32 characters".to_string())
+        );
+        assert_eq!(new_error, insert_error);
+    }
+
+    #[test]
+    fn table_insert_values_with_select() {
+        let mut table = Table::new();
+
+        table
+            .insert_values(1, "otaviopace", "otavio@gmail.com")
+            .unwrap();
+
+        let output = run_command(&mut table, "select".to_string()).unwrap();
+
+        assert_eq!(output, "(1, otaviopace, otavio@gmail.com)\nExecuted.\n");
+    }
+
+    #[test]
+    fn run_command_select_custom_row_separator() {
+        let mut table = Table::new();
+        let mut format = Format::new();
+
+        run_command_with_format(
+            &mut table,
+            &mut format,
+            "insert 1 john john@mailbox.com".to_string(),
+        )
+        .unwrap();
+        run_command_with_format(
+            &mut table,
+            &mut format,
+            "insert 2 jane jane@mailbox.com".to_string(),
+        )
+        .unwrap();
+        run_command_with_format(&mut table, &mut format, ".rowsep ;".to_string()).unwrap();
+
+        let output =
+            run_command_with_format(&mut table, &mut format, "select".to_string()).unwrap();
+
+        assert_eq!(
+            output,
+            "(1, john, john@mailbox.com);(2, jane, jane@mailbox.com)\nExecuted.\n"
+        );
+
+        run_command_with_format(&mut table, &mut format, ".rowsep \\n".to_string()).unwrap();
+
+        let output =
+            run_command_with_format(&mut table, &mut format, "select".to_string()).unwrap();
+
+        assert_eq!(
+            output,
+            "(1, john, john@mailbox.com)\n(2, jane, jane@mailbox.com)\nExecuted.\n"
         );
     }
+
+    #[test]
+    fn run_command_select_order_by_rowid() {
+        let mut table = Table::new();
+
+        for input in &[
+            "insert 3 carol carol@mail.com",
+            "insert 1 alice alice@mail.com",
+            "insert 2 bob bob@mail.com",
+        ] {
+            run_command(&mut table, input.to_string()).unwrap();
+        }
+
+        let output = run_command(&mut table, "select order by id".to_string()).unwrap();
+
+        assert_eq!(
+            output,
+            "(1, alice, alice@mail.com)\n\
+             (2, bob, bob@mail.com)\n\
+             (3, carol, carol@mail.com)\n\
+             Executed.\n"
+        );
+
+        let output = run_command(&mut table, "select order by rowid".to_string()).unwrap();
+
+        assert_eq!(
+            output,
+            "(3, carol, carol@mail.com)\n\
+             (1, alice, alice@mail.com)\n\
+             (2, bob, bob@mail.com)\n\
+             Executed.\n"
+        );
+    }
+
+    #[test]
+    fn run_command_select_insert_mode() {
+        let mut table = Table::new();
+        let mut format = Format::new();
+
+        run_command_with_format(
+            &mut table,
+            &mut format,
+            "insert 2 jane jane@mailbox.com".to_string(),
+        )
+        .unwrap();
+        run_command_with_format(
+            &mut table,
+            &mut format,
+            "insert 1 john john@mailbox.com".to_string(),
+        )
+        .unwrap();
+        run_command_with_format(&mut table, &mut format, ".mode insert".to_string()).unwrap();
+
+        let output =
+            run_command_with_format(&mut table, &mut format, "select order by id".to_string())
+                .unwrap();
+
+        assert_eq!(
+            output,
+            "insert 1 john john@mailbox.com\ninsert 2 jane jane@mailbox.com\nExecuted.\n"
+        );
+
+        let mut other_table = Table::new();
+
+        for line in output.lines().filter(|line| line.starts_with("insert")) {
+            run_command(&mut other_table, line.to_string()).unwrap();
+        }
+
+        run_command_with_format(&mut table, &mut format, ".mode list".to_string()).unwrap();
+
+        assert_eq!(
+            run_command(&mut other_table, "select".to_string()),
+            run_command_with_format(&mut table, &mut format, "select order by id".to_string())
+        );
+    }
+
+    #[test]
+    fn run_command_unknown_mode() {
+        let mut table = Table::new();
+        let mut format = Format::new();
+
+        let error = run_command_with_format(&mut table, &mut format, ".mode bogus".to_string())
+            .unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::SyntaxError("Syntax error. Unknown mode 'bogus'".to_string())
+        );
+    }
+
+    #[test]
+    fn run_command_insert_missing_username_position() {
+        let mut table = Table::new();
+
+        let error = run_command(&mut table, "insert 12".to_string()).unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::SyntaxError(
+                "Syntax error. Failed to parse 'username' from '' at position 9".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn run_command_insert_unexpected_token_position() {
+        let mut table = Table::new();
+
+        let error = run_command(
+            &mut table,
+            "insert 1 john john@mailbox.com extra".to_string(),
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::SyntaxError("Syntax error. Unexpected 'extra' at position 31".to_string())
+        );
+    }
+
+    #[test]
+    fn run_command_select_unexpected_token_position() {
+        let mut table = Table::new();
+
+        let error = run_command(&mut table, "select order by id sideways".to_string()).unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::SyntaxError(
+                "Syntax error. Unexpected 'sideways' in select at position 19".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn run_command_select_empty_table_in_every_mode() {
+        let mut table = Table::new();
+        let mut format = Format::new();
+
+        for mode in &["list", "insert", "line"] {
+            run_command_with_format(&mut table, &mut format, format!(".mode {}", mode)).unwrap();
+
+            let output =
+                run_command_with_format(&mut table, &mut format, "select".to_string()).unwrap();
+
+            assert_eq!(output, "Executed.\n", "mode {}", mode);
+        }
+
+        let output = run_command(
+            &mut table,
+            "select username, count group by username".to_string(),
+        )
+        .unwrap();
+
+        assert_eq!(output, "Executed.\n");
+    }
+
+    #[test]
+    fn run_command_insert_if_not_exists_created() {
+        let mut table = Table::new();
+
+        let output = run_command(
+            &mut table,
+            "insert if not exists 1 john john@mailbox.com".to_string(),
+        )
+        .unwrap();
+
+        assert_eq!(output, "Created: (1, john, john@mailbox.com)\nExecuted.\n");
+
+        let output = run_command(&mut table, "select".to_string()).unwrap();
+
+        assert_eq!(output, "(1, john, john@mailbox.com)\nExecuted.\n");
+    }
+
+    #[test]
+    fn run_command_insert_if_not_exists_already_exists() {
+        let mut table = Table::new();
+
+        run_command(&mut table, "insert 1 john john@mailbox.com".to_string()).unwrap();
+
+        let output = run_command(
+            &mut table,
+            "insert if not exists 1 jane jane@mailbox.com".to_string(),
+        )
+        .unwrap();
+
+        assert_eq!(output, "Exists: (1, john, john@mailbox.com)\nExecuted.\n");
+
+        let output = run_command(&mut table, "select".to_string()).unwrap();
+
+        assert_eq!(output, "(1, john, john@mailbox.com)\nExecuted.\n");
+    }
+
+    #[test]
+    fn run_command_insert_if_without_not_exists() {
+        let mut table = Table::new();
+
+        let error = run_command(
+            &mut table,
+            "insert if exists 1 john john@mailbox.com".to_string(),
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::SyntaxError("Syntax error. Expected 'not' at position 10".to_string())
+        );
+    }
+
+    #[test]
+    fn command_kind_of_built_in_commands() {
+        let mut registry = CommandRegistry::new();
+        registry.register_command("ping", |_input, _table| Ok("pong".to_string()));
+
+        let expected_kinds = [
+            (".exit", CommandKind::Meta(".exit")),
+            (".memory", CommandKind::Meta(".memory")),
+            (".cache on", CommandKind::Meta(".cache")),
+            (".stats", CommandKind::Meta(".stats")),
+            (".rowsep ;", CommandKind::Meta(".rowsep")),
+            (".mode list", CommandKind::Meta(".mode")),
+            (".format id-width 4", CommandKind::Meta(".format")),
+            (".readonly on", CommandKind::Meta(".readonly")),
+            (".seed 42", CommandKind::Meta(".seed")),
+            (
+                ".allowdomain example.com",
+                CommandKind::Meta(".allowdomain"),
+            ),
+            (".undo", CommandKind::Meta(".undo")),
+            (".comment users", CommandKind::Meta(".comment")),
+            (".numberformat hex", CommandKind::Meta(".numberformat")),
+            (".dedup on", CommandKind::Meta(".dedup")),
+            (".escape on", CommandKind::Meta(".escape")),
+            (".trailer off", CommandKind::Meta(".trailer")),
+            (".intern on", CommandKind::Meta(".intern")),
+            (".defaultemail a@x.com", CommandKind::Meta(".defaultemail")),
+            (".truncate on", CommandKind::Meta(".truncate")),
+            (".maxoutput 1024", CommandKind::Meta(".maxoutput")),
+            (".timeout 500", CommandKind::Meta(".timeout")),
+            (".peek 3", CommandKind::Meta(".peek")),
+            (".rowid 5", CommandKind::Meta(".rowid")),
+            (".validate select", CommandKind::Meta(".validate")),
+            (".histogram id 10", CommandKind::Meta(".histogram")),
+            ("insert 1 john john@mailbox.com", CommandKind::Insert),
+            ("select order by id", CommandKind::Select),
+            ("select pages pagesize 10", CommandKind::Select),
+            ("select sample 3", CommandKind::Select),
+            ("select since 3", CommandKind::Select),
+            (";", CommandKind::Empty),
+            ("delete where id < 3", CommandKind::Delete),
+            ("update id 1 to 2", CommandKind::Update),
+            ("ping", CommandKind::Custom),
+        ];
+
+        for (input, expected_kind) in &expected_kinds {
+            let command = build_command(input, &registry).unwrap();
+
+            assert_eq!(command.command_kind(), *expected_kind, "input {}", input);
+        }
+    }
+
+    #[test]
+    fn run_command_select_pages() {
+        let mut table = Table::new();
+
+        let output = run_command(&mut table, "select pages pagesize 2".to_string()).unwrap();
+
+        assert_eq!(output, "0\nExecuted.\n");
+
+        for id in 1..=4 {
+            run_command(
+                &mut table,
+                format!("insert {} user{} user{}@mail.com", id, id, id),
+            )
+            .unwrap();
+        }
+
+        let output = run_command(&mut table, "select pages pagesize 2".to_string()).unwrap();
+
+        assert_eq!(output, "2\nExecuted.\n");
+
+        run_command(&mut table, "insert 5 user5 user5@mail.com".to_string()).unwrap();
+
+        let output = run_command(&mut table, "select pages pagesize 2".to_string()).unwrap();
+
+        assert_eq!(output, "3\nExecuted.\n");
+
+        let output = run_command(&mut table, "select pages pagesize 50".to_string()).unwrap();
+
+        assert_eq!(output, "1\nExecuted.\n");
+    }
+
+    #[test]
+    fn run_command_select_pages_zero_page_size() {
+        let mut table = Table::new();
+
+        let error = run_command(&mut table, "select pages pagesize 0".to_string()).unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::SyntaxError(
+                "Syntax error. 'pagesize' must be greater than 0 at position 22".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn run_command_select_order_by_id_desc_limit() {
+        let mut table = Table::new();
+
+        for id in &[4, 9, 1, 7, 3, 8, 2, 6, 5, 10, 12, 11] {
+            run_command(
+                &mut table,
+                format!("insert {} user{} user{}@mail.com", id, id, id),
+            )
+            .unwrap();
+        }
+
+        let output =
+            run_command(&mut table, "select order by id desc limit 3".to_string()).unwrap();
+
+        assert_eq!(
+            output,
+            "(12, user12, user12@mail.com)\n\
+             (11, user11, user11@mail.com)\n\
+             (10, user10, user10@mail.com)\n\
+             Executed.\n"
+        );
+
+        let output = run_command(&mut table, "select limit 2".to_string()).unwrap();
+
+        assert_eq!(
+            output,
+            "(4, user4, user4@mail.com)\n(9, user9, user9@mail.com)\nExecuted.\n"
+        );
+    }
+
+    #[test]
+    fn run_command_select_id_width() {
+        let mut table = Table::new();
+        let mut format = Format::new();
+
+        run_command_with_format(
+            &mut table,
+            &mut format,
+            "insert 7 john john@mailbox.com".to_string(),
+        )
+        .unwrap();
+        run_command(
+            &mut table,
+            "insert 1234567 jane jane@mailbox.com".to_string(),
+        )
+        .unwrap();
+        run_command_with_format(&mut table, &mut format, ".format id-width 6".to_string()).unwrap();
+
+        let output =
+            run_command_with_format(&mut table, &mut format, "select".to_string()).unwrap();
+
+        assert_eq!(
+            output,
+            "(     7, john, john@mailbox.com)\n(1234567, jane, jane@mailbox.com)\nExecuted.\n"
+        );
+
+        run_command_with_format(&mut table, &mut format, ".format id-width 0".to_string()).unwrap();
+
+        let output =
+            run_command_with_format(&mut table, &mut format, "select".to_string()).unwrap();
+
+        assert_eq!(
+            output,
+            "(7, john, john@mailbox.com)\n(1234567, jane, jane@mailbox.com)\nExecuted.\n"
+        );
+
+        let error = run_command_with_format(
+            &mut table,
+            &mut format,
+            ".format id-width 70000".to_string(),
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::SyntaxError("Syntax error. 'id-width' can't be more than 64".to_string())
+        );
+
+        run_command_with_format(&mut table, &mut format, ".format id-width 64".to_string())
+            .unwrap();
+
+        let output =
+            run_command_with_format(&mut table, &mut format, "select limit 1".to_string()).unwrap();
+
+        assert_eq!(output.find('7'), Some(64));
+    }
+
+    #[test]
+    fn run_command_insert_into_multiple_tuples() {
+        let mut table = Table::new();
+
+        let output = run_command(
+            &mut table,
+            "insert into users values (1,a,a@x.com),(2, b, b@x.com), (3,c,c@x.com)".to_string(),
+        )
+        .unwrap();
+
+        assert_eq!(output, "Executed.\n");
+
+        let output = run_command(&mut table, "select".to_string()).unwrap();
+
+        assert_eq!(
+            output,
+            "(1, a, a@x.com)\n(2, b, b@x.com)\n(3, c, c@x.com)\nExecuted.\n"
+        );
+    }
+
+    #[test]
+    fn run_command_insert_into_invalid_tuple_inserts_nothing() {
+        let mut table = Table::new();
+
+        let error = run_command(
+            &mut table,
+            "insert into users values (1,a,a@x.com),(two,b,b@x.com),(3,c,c@x.com)".to_string(),
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::SyntaxError(
+                "Syntax error. Failed to parse 'id' from 'two' at position 40".to_string()
+            )
+        );
+
+        let output = run_command(&mut table, "select".to_string()).unwrap();
+
+        assert_eq!(output, "Executed.\n");
+    }
+
+    #[test]
+    fn run_command_insert_into_unknown_table() {
+        let mut table = Table::new();
+
+        let error = run_command(
+            &mut table,
+            "insert into accounts values (1,a,a@x.com)".to_string(),
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::SyntaxError("Syntax error. Expected 'users' at position 12".to_string())
+        );
+    }
+
+    #[test]
+    fn run_command_readonly() {
+        let mut table = Table::new();
+
+        run_command(&mut table, "insert 1 john john@mailbox.com".to_string()).unwrap();
+        run_command(&mut table, ".readonly on".to_string()).unwrap();
+
+        let error =
+            run_command(&mut table, "insert 2 mary mary@mailbox.com".to_string()).unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::ReadOnly("Read-only mode. 'insert' can't change the table".to_string())
+        );
+
+        let output = run_command(&mut table, "select".to_string()).unwrap();
+
+        assert_eq!(output, "(1, john, john@mailbox.com)\nExecuted.\n");
+
+        let error = run_command(&mut table, ".comment users anything".to_string()).unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::ReadOnly("Read-only mode. '.comment' can't change the table".to_string())
+        );
+
+        run_command(&mut table, ".readonly off".to_string()).unwrap();
+
+        let output = run_command(&mut table, "insert 2 mary mary@mailbox.com".to_string());
+
+        assert_eq!(output, Ok("Executed.\n".to_string()));
+    }
+
+    #[test]
+    fn run_command_new_readonly_cant_be_turned_off() {
+        let mut table = Table::new_readonly();
+        let mut registry = CommandRegistry::new();
+        registry.register_command("wipe", |_input, table| {
+            table.delete_rows(|_| true);
+            Ok("".to_string())
+        });
+
+        let error = run_command(&mut table, ".readonly off".to_string()).unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::ReadOnly("Read-only mode. The table was created read-only".to_string())
+        );
+        assert!(table.is_readonly());
+
+        let error = registry
+            .run_command(&mut table, "wipe".to_string())
+            .unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::ReadOnly("Read-only mode. 'custom command' can't change the table".to_string())
+        );
+
+        run_command(&mut table, ".readonly on".to_string()).unwrap();
+        run_command(&mut table, "select".to_string()).unwrap();
+
+        for (input, expected) in &[
+            (
+                ".comment users anything",
+                "Read-only mode. '.comment' can't change the table",
+            ),
+            (
+                ".timeout 0",
+                "Read-only mode. '.timeout' can't be changed on a table created read-only",
+            ),
+            (
+                ".maxoutput 0",
+                "Read-only mode. '.maxoutput' can't be changed on a table created read-only",
+            ),
+        ] {
+            let error = run_command(&mut table, input.to_string()).unwrap_err();
+
+            assert_eq!(
+                error,
+                Error::ReadOnly(expected.to_string()),
+                "input {}",
+                input
+            );
+        }
+
+        assert_eq!(
+            run_command(&mut table, ".comment users".to_string()),
+            Ok("".to_string())
+        );
+    }
+
+    #[test]
+    fn run_command_select_length() {
+        let mut table = Table::new();
+
+        run_command(&mut table, "insert 1 josé jose@mailbox.com".to_string()).unwrap();
+        run_command(&mut table, "insert 2 ümlaut u@mail.com".to_string()).unwrap();
+        run_command(&mut table, "insert 3 john john@mailbox.com".to_string()).unwrap();
+
+        let output = run_command(
+            &mut table,
+            "select id, length(username) order by id desc".to_string(),
+        )
+        .unwrap();
+
+        assert_eq!(output, "(3, 4)\n(2, 6)\n(1, 4)\nExecuted.\n");
+
+        let output = run_command(
+            &mut table,
+            "select length(username), count group by username limit 1".to_string(),
+        )
+        .unwrap();
+
+        assert_eq!(output, "(4, 1)\nExecuted.\n");
+    }
+
+    #[test]
+    fn run_command_select_length_of_non_text_column() {
+        let mut table = Table::new();
+
+        let error = run_command(&mut table, "select length(id)".to_string()).unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::SyntaxError(
+                "Syntax error. 'length' requires a text column, not 'id' at position 14"
+                    .to_string()
+            )
+        );
+
+        let error = run_command(&mut table, "select username, count".to_string()).unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::SyntaxError("Syntax error. 'count' requires a 'group by'".to_string())
+        );
+    }
+
+    #[test]
+    fn run_command_select_sample_is_deterministic() {
+        let mut table = Table::new();
+
+        insert_users(&mut table, 1..=20);
+
+        run_command(&mut table, ".seed 7".to_string()).unwrap();
+        let first = run_command(&mut table, "select sample 5".to_string()).unwrap();
+        let second = run_command(&mut table, "select sample 5".to_string()).unwrap();
+
+        run_command(&mut table, ".seed 7".to_string()).unwrap();
+        let again = run_command(&mut table, "select sample 5".to_string()).unwrap();
+
+        assert_eq!(first, again);
+        assert_ne!(first, second);
+        assert_eq!(first.lines().count(), 6);
+
+        let output = run_command(&mut table, "select sample 50".to_string()).unwrap();
+        let all = run_command(&mut table, "select".to_string()).unwrap();
+
+        assert_eq!(output, all);
+    }
+
+    #[test]
+    fn execute_script_yields_each_output() {
+        let mut table = Table::new();
+        let script = "insert 1 john john@mailbox.com\n\nvacuum users\nselect\n";
+
+        let outputs: Vec<Result<CommandOutput, Error>> =
+            execute_script(&mut table, script).collect();
+
+        assert_eq!(
+            outputs,
+            vec![
+                Ok(CommandOutput::Output("Executed.\n".to_string())),
+                Err(Error::UnrecognizedStatement(
+                    "Unrecognized keyword at start of 'vacuum users'".to_string()
+                )),
+                Ok(CommandOutput::Output(
+                    "(1, john, john@mailbox.com)\nExecuted.\n".to_string()
+                )),
+            ]
+        );
+    }
+
+    #[test]
+    fn execute_script_stops_early() {
+        let mut table = Table::new();
+        let script = "insert 1 john john@mailbox.com\ninsert 2 mary mary@mailbox.com\n.exit";
+
+        let first = execute_script(&mut table, script).next();
+
+        assert_eq!(
+            first,
+            Some(Ok(CommandOutput::Output("Executed.\n".to_string())))
+        );
+        assert_eq!(table.count(), 1);
+    }
+
+    #[test]
+    fn run_command_allowed_email_domains() {
+        let mut table = Table::new();
+
+        run_command(&mut table, "insert 1 john john@other.com".to_string()).unwrap();
+        run_command(&mut table, ".allowdomain example.com".to_string()).unwrap();
+        run_command(&mut table, ".allowdomain mail.org".to_string()).unwrap();
+
+        let output = run_command(&mut table, "insert 2 a a@Example.com".to_string());
+
+        assert_eq!(output, Ok("Executed.\n".to_string()));
+
+        let output = run_command(&mut table, "insert 3 b b@mail.org".to_string());
+
+        assert_eq!(output, Ok("Executed.\n".to_string()));
+
+        let error = run_command(&mut table, "insert 4 c c@other.com".to_string()).unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::SyntaxError(
+                "Syntax error. Email domain of 'c@other.com' isn't allowed".to_string()
+            )
+        );
+
+        let error = table
+            .insert_values(5, "d", "d@example.com.evil")
+            .unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::SyntaxError(
+                "Syntax error. Email domain of 'd@example.com.evil' isn't allowed".to_string()
+            )
+        );
+        assert_eq!(table.count(), 3);
+    }
+
+    #[test]
+    fn run_command_undo_last_insert() {
+        let mut table = Table::new();
+
+        let error = run_command(&mut table, ".undo".to_string()).unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::SyntaxError("Syntax error. There's nothing to undo".to_string())
+        );
+
+        run_command(&mut table, "insert 1 john john@mailbox.com".to_string()).unwrap();
+        run_command(
+            &mut table,
+            "insert into users values (2, a, a@x.com), (3, b, b@x.com)".to_string(),
+        )
+        .unwrap();
+        run_command(&mut table, "insert 4 mary".to_string()).unwrap_err();
+        run_command(&mut table, ".undo".to_string()).unwrap();
+
+        let output = run_command(&mut table, "select".to_string()).unwrap();
+
+        assert_eq!(output, "(1, john, john@mailbox.com)\nExecuted.\n");
+
+        let error = run_command(&mut table, ".undo".to_string()).unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::SyntaxError("Syntax error. There's nothing to undo".to_string())
+        );
+    }
+
+    #[test]
+    fn run_command_undo_every_kind_of_insert() {
+        let mut table = Table::new();
+
+        run_command(&mut table, "insert 1 john john@mailbox.com".to_string()).unwrap();
+        table.insert_values(2, "mary", "mary@mailbox.com").unwrap();
+        run_command(&mut table, ".undo".to_string()).unwrap();
+
+        let output = run_command(&mut table, "select ids".to_string()).unwrap();
+
+        assert_eq!(output, "1\nExecuted.\n");
+
+        run_command(&mut table, "insert 3 jane jane@mailbox.com".to_string()).unwrap();
+
+        let output = run_command(
+            &mut table,
+            "insert if not exists 1 john john@mailbox.com".to_string(),
+        )
+        .unwrap();
+
+        assert_eq!(output, "Exists: (1, john, john@mailbox.com)\nExecuted.\n");
+
+        run_command(&mut table, ".undo".to_string()).unwrap();
+
+        let output = run_command(&mut table, "select ids".to_string()).unwrap();
+
+        assert_eq!(output, "1\nExecuted.\n");
+    }
+
+    #[test]
+    fn run_command_undo_update_with_duplicate_ids() {
+        let mut table = Table::new();
+
+        run_command(&mut table, "insert 5 john john@mailbox.com".to_string()).unwrap();
+        run_command(&mut table, "insert 5 mary mary@mailbox.com".to_string()).unwrap();
+        run_command(&mut table, "update id 5 to 6".to_string()).unwrap();
+
+        let output = run_command(&mut table, "select".to_string()).unwrap();
+
+        assert_eq!(
+            output,
+            "(6, john, john@mailbox.com)\n(5, mary, mary@mailbox.com)\nExecuted.\n"
+        );
+
+        run_command(&mut table, ".undo".to_string()).unwrap();
+
+        let output = run_command(&mut table, "select".to_string()).unwrap();
+
+        assert_eq!(
+            output,
+            "(5, john, john@mailbox.com)\n(5, mary, mary@mailbox.com)\nExecuted.\n"
+        );
+    }
+
+    #[test]
+    fn run_command_select_concat() {
+        let mut table = Table::new();
+
+        run_command(&mut table, "insert 1 john john@mailbox.com".to_string()).unwrap();
+        run_command(&mut table, "insert 2 mary mary@mailbox.com".to_string()).unwrap();
+
+        let output = run_command(
+            &mut table,
+            "select id, concat(username, email, username)".to_string(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            output,
+            "(1, johnjohn@mailbox.comjohn)\n(2, marymary@mailbox.commary)\nExecuted.\n"
+        );
+
+        let error = run_command(&mut table, "select concat(username, id)".to_string()).unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::SyntaxError(
+                "Syntax error. 'concat' requires a text column, not 'id' at position 24"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn run_command_table_comment() {
+        let mut table = Table::new();
+
+        let output = run_command(&mut table, ".comment users".to_string());
+
+        assert_eq!(output, Ok("".to_string()));
+
+        run_command(
+            &mut table,
+            ".comment users \"user  accounts table\"".to_string(),
+        )
+        .unwrap();
+
+        let output = run_command(&mut table, ".comment users".to_string());
+
+        assert_eq!(output, Ok("user  accounts table\n".to_string()));
+
+        run_command(&mut table, ".comment users accounts".to_string()).unwrap();
+
+        let output = run_command(&mut table, ".comment users".to_string());
+
+        assert_eq!(output, Ok("accounts\n".to_string()));
+
+        let error = run_command(&mut table, ".comment accounts".to_string()).unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::SyntaxError("Syntax error. Unknown table 'accounts'".to_string())
+        );
+    }
+
+    #[test]
+    fn run_command_number_format() {
+        let mut table = Table::new();
+        let mut format = Format::new();
+
+        run_command_with_format(&mut table, &mut format, ".numberformat hex".to_string()).unwrap();
+        run_command_with_format(
+            &mut table,
+            &mut format,
+            "insert 31 john john@mailbox.com".to_string(),
+        )
+        .unwrap();
+
+        let output =
+            run_command_with_format(&mut table, &mut format, "select".to_string()).unwrap();
+
+        assert_eq!(output, "(0x1f, john, john@mailbox.com)\nExecuted.\n");
+
+        run_command_with_format(&mut table, &mut format, ".mode insert".to_string()).unwrap();
+
+        let output =
+            run_command_with_format(&mut table, &mut format, "select".to_string()).unwrap();
+
+        assert_eq!(output, "insert 31 john john@mailbox.com\nExecuted.\n");
+
+        run_command_with_format(&mut table, &mut format, ".mode list".to_string()).unwrap();
+        run_command_with_format(&mut table, &mut format, ".numberformat dec".to_string()).unwrap();
+
+        let output =
+            run_command_with_format(&mut table, &mut format, "select".to_string()).unwrap();
+
+        assert_eq!(output, "(31, john, john@mailbox.com)\nExecuted.\n");
+
+        let error =
+            run_command_with_format(&mut table, &mut format, ".numberformat octal".to_string())
+                .unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::SyntaxError("Syntax error. Unknown number format 'octal'".to_string())
+        );
+    }
+
+    #[test]
+    fn run_command_select_dedup() {
+        let mut table = Table::new();
+        let mut format = Format::new();
+
+        run_command_with_format(
+            &mut table,
+            &mut format,
+            "insert 1 john john@mailbox.com".to_string(),
+        )
+        .unwrap();
+        run_command_with_format(
+            &mut table,
+            &mut format,
+            "insert 2 mary mary@mailbox.com".to_string(),
+        )
+        .unwrap();
+        run_command_with_format(
+            &mut table,
+            &mut format,
+            "insert 1 johnny johnny@mailbox.com".to_string(),
+        )
+        .unwrap();
+
+        let output =
+            run_command_with_format(&mut table, &mut format, "select".to_string()).unwrap();
+
+        assert_eq!(
+            output,
+            "(1, john, john@mailbox.com)\n(2, mary, mary@mailbox.com)\n\
+             (1, johnny, johnny@mailbox.com)\nExecuted.\n"
+        );
+
+        run_command_with_format(&mut table, &mut format, ".dedup on".to_string()).unwrap();
+
+        let output =
+            run_command_with_format(&mut table, &mut format, "select".to_string()).unwrap();
+
+        assert_eq!(
+            output,
+            "(2, mary, mary@mailbox.com)\n(1, johnny, johnny@mailbox.com)\nExecuted.\n"
+        );
+    }
+
+    #[test]
+    fn run_command_update_id() {
+        let mut table = Table::new();
+
+        run_command(&mut table, "insert 3 john john@mailbox.com".to_string()).unwrap();
+        run_command(&mut table, "insert 7 mary mary@mailbox.com".to_string()).unwrap();
+
+        let error = run_command(&mut table, "update id 3 to 7".to_string()).unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::SyntaxError("Syntax error. A row with id 7 already exists".to_string())
+        );
+
+        let output = run_command(&mut table, "select".to_string()).unwrap();
+
+        assert_eq!(
+            output,
+            "(3, john, john@mailbox.com)\n(7, mary, mary@mailbox.com)\nExecuted.\n"
+        );
+
+        let output = run_command(&mut table, "update id 3 to 5".to_string());
+
+        assert_eq!(output, Ok("Executed.\n".to_string()));
+
+        let output = run_command(&mut table, "select".to_string()).unwrap();
+
+        assert_eq!(
+            output,
+            "(5, john, john@mailbox.com)\n(7, mary, mary@mailbox.com)\nExecuted.\n"
+        );
+
+        let error = run_command(&mut table, "update id 3 to 4".to_string()).unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::SyntaxError("Syntax error. There's no row with id 3".to_string())
+        );
+
+        run_command(&mut table, ".undo".to_string()).unwrap();
+
+        let output = run_command(&mut table, "select".to_string()).unwrap();
+
+        assert_eq!(
+            output,
+            "(3, john, john@mailbox.com)\n(7, mary, mary@mailbox.com)\nExecuted.\n"
+        );
+    }
+
+    #[test]
+    fn run_command_select_max_output() {
+        let mut table = Table::new();
+        let mut format = Format::new();
+
+        for id in 1..=1000 {
+            table.insert_values(id, "user", "user@mail.com").unwrap();
+        }
+
+        // every line is `(N, user, user@mail.com)`, 24 bytes for ids up to 9, plus the separator
+        run_command_with_format(&mut table, &mut format, ".maxoutput 60".to_string()).unwrap();
+
+        let output =
+            run_command_with_format(&mut table, &mut format, "select".to_string()).unwrap();
+
+        assert_eq!(
+            output,
+            "(1, user, user@mail.com)\n(2, user, user@mail.com)\n-- output truncated\nExecuted.\n"
+        );
+
+        run_command_with_format(&mut table, &mut format, ".maxoutput 10".to_string()).unwrap();
+
+        let output =
+            run_command_with_format(&mut table, &mut format, "select".to_string()).unwrap();
+
+        assert_eq!(output, "-- output truncated\nExecuted.\n");
+
+        run_command_with_format(&mut table, &mut format, ".maxoutput 0".to_string()).unwrap();
+
+        let output =
+            run_command_with_format(&mut table, &mut format, "select".to_string()).unwrap();
+
+        assert_eq!(output.lines().count(), 1001);
+    }
+
+    #[test]
+    fn run_command_peek() {
+        let mut table = Table::new();
+
+        insert_users(&mut table, [4, 2, 6, 1, 5, 3, 7]);
+
+        let output = run_command(&mut table, ".peek 2".to_string()).unwrap();
+
+        assert_eq!(
+            output,
+            "(1, user1, user@mail.com)\n(2, user2, user@mail.com)\n...\n\
+             (6, user6, user@mail.com)\n(7, user7, user@mail.com)\n"
+        );
+
+        let output = run_command(&mut table, ".peek 4".to_string()).unwrap();
+
+        assert_eq!(output.lines().count(), 7);
+        assert!(!output.contains("..."));
+        assert!(output.starts_with("(1, user1, user@mail.com)\n(2, user2"));
+
+        assert_eq!(
+            run_command(&mut table, ".peek 0".to_string()),
+            Err(Error::SyntaxError(
+                "Syntax error. '.peek' must be greater than 0".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn run_command_bare_select_is_in_rowid_order() {
+        let mut table = Table::new();
+
+        insert_users(&mut table, [5, 1, 3]);
+
+        let output = run_command(&mut table, "select".to_string()).unwrap();
+
+        assert_eq!(
+            output,
+            "(5, user5, user@mail.com)\n(1, user1, user@mail.com)\n\
+             (3, user3, user@mail.com)\nExecuted.\n"
+        );
+
+        let output = run_command(&mut table, "select limit 2".to_string()).unwrap();
+
+        assert_eq!(
+            output,
+            "(5, user5, user@mail.com)\n(1, user1, user@mail.com)\nExecuted.\n"
+        );
+    }
+
+    #[test]
+    fn run_command_intern_reduces_memory() {
+        let insert_rows = |table: &mut Table| {
+            for id in 1..=100 {
+                run_command(
+                    table,
+                    format!("insert {} a_repeated_username shared@mailbox.com", id),
+                )
+                .unwrap();
+            }
+        };
+
+        let mut table = Table::new();
+        insert_rows(&mut table);
+        let plain = parse_memory_output(&run_command(&mut table, ".memory".to_string()).unwrap());
+
+        let mut table = Table::new();
+        run_command(&mut table, ".intern on".to_string()).unwrap();
+        insert_rows(&mut table);
+        let interned =
+            parse_memory_output(&run_command(&mut table, ".memory".to_string()).unwrap());
+
+        assert!(interned < plain, "{} < {}", interned, plain);
+        assert_eq!(
+            run_command(&mut table, ".stats".to_string()),
+            Ok("cache hits: 0\nintern dedup ratio: 0.99\n".to_string())
+        );
+
+        let output = run_command(&mut table, "select limit 1".to_string()).unwrap();
+
+        assert_eq!(
+            output,
+            "(1, a_repeated_username, shared@mailbox.com)\nExecuted.\n"
+        );
+    }
+
+    #[test]
+    fn run_command_intern_prunes_removed_values() {
+        let mut table = Table::new();
+        run_command(&mut table, ".intern on".to_string()).unwrap();
+
+        let memory = |table: &mut Table| {
+            parse_memory_output(&run_command(table, ".memory".to_string()).unwrap())
+        };
+
+        for id in 1..=100 {
+            let email = format!("user{}@{}.com", id, "a".repeat(100));
+            run_command(&mut table, format!("insert {} user{} {}", id, id, email)).unwrap();
+        }
+
+        let full = memory(&mut table);
+
+        run_command(&mut table, "delete where id > 0".to_string()).unwrap();
+
+        // the deleted values are kept for `.undo` until another change
+        assert!(memory(&mut table) + 10_000 > full);
+
+        run_command(&mut table, "insert 1 john john@mailbox.com".to_string()).unwrap();
+
+        let pruned = memory(&mut table);
+
+        assert!(pruned + 10_000 < full, "{} < {}", pruned, full);
+
+        run_command(&mut table, ".undo".to_string()).unwrap();
+
+        assert!(memory(&mut table) < pruned);
+    }
+
+    #[test]
+    fn run_command_select_id_arithmetic() {
+        let mut table = Table::new();
+
+        run_command(&mut table, "insert 1 john john@mailbox.com".to_string()).unwrap();
+        run_command(
+            &mut table,
+            "insert 4294967295 mary mary@mailbox.com".to_string(),
+        )
+        .unwrap();
+
+        let output = run_command(&mut table, "select id + 1000, username".to_string()).unwrap();
+
+        assert_eq!(output, "(1001, john)\n(4294968295, mary)\nExecuted.\n");
+
+        let error = run_command(&mut table, "select id - 1000".to_string()).unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::OutOfRange("Out of range. 'id - 1000' overflows for id 1".to_string())
+        );
+
+        let error = run_command(&mut table, format!("select id + {}", usize::MAX)).unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::OutOfRange(format!(
+                "Out of range. 'id + {}' overflows for id 1",
+                usize::MAX
+            ))
+        );
+
+        let error = run_command(&mut table, "select username + 1".to_string()).unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::SyntaxError(
+                "Syntax error. '+' requires a numeric column, not 'username' at position 7"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn run_command_custom_row_validators() {
+        let mut table = Table::new();
+
+        table.add_validator(|row| match row.username().chars().next() {
+            Some(first) if first.is_ascii_digit() => Err(Error::Custom(format!(
+                "Username '{}' can't start with a digit",
+                row.username()
+            ))),
+            _ => Ok(()),
+        });
+        table.add_validator(|row| match row.id() {
+            0 => Err(Error::Custom("Id can't be 0".to_string())),
+            _ => Ok(()),
+        });
+
+        let error =
+            run_command(&mut table, "insert 1 7john john@mailbox.com".to_string()).unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::Custom("Username '7john' can't start with a digit".to_string())
+        );
+
+        run_command(&mut table, "insert 1 john john@mailbox.com".to_string()).unwrap();
+
+        let error = run_command(&mut table, "update id 1 to 0".to_string()).unwrap_err();
+
+        assert_eq!(error, Error::Custom("Id can't be 0".to_string()));
+
+        let output = run_command(&mut table, "select".to_string()).unwrap();
+
+        assert_eq!(output, "(1, john, john@mailbox.com)\nExecuted.\n");
+    }
+
+    #[test]
+    fn select_lines_matches_select_output() {
+        let mut table = Table::new();
+
+        insert_users(&mut table, [3, 1, 2, 1]);
+
+        for input in &[
+            "select",
+            "select limit 2",
+            "select order by id desc limit 3",
+            "select username, count group by username",
+            "select id + 1, length(username)",
+        ] {
+            let query = input.parse::<Query>().unwrap();
+            let lines: Vec<String> = select_lines(&table, &Format::new(), &query)
+                .unwrap()
+                .collect();
+
+            let output = run_command(&mut table, input.to_string()).unwrap();
+
+            assert_eq!(
+                format!("{}\nExecuted.\n", lines.join("\n")),
+                output,
+                "input {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn meta_command_from_str_unrecognized() {
+        for input in &[".", ".bogus", ".bogus on"] {
+            let error = run_command(&mut Table::new(), input.to_string()).unwrap_err();
+
+            assert_eq!(
+                error,
+                Error::UnrecognizedCommand(format!("Unrecognized meta command '{}'", input))
+            );
+        }
+    }
+
+    #[test]
+    fn meta_command_from_str_wrong_arguments() {
+        for (input, usage) in &[
+            (".cache maybe", ".cache on|off"),
+            (".format id-width", ".format id-width <width>"),
+            (".format", ".format id-width <width>"),
+            (".memory now", ".memory"),
+            (".peek", ".peek <size>"),
+        ] {
+            let error = run_command(&mut Table::new(), input.to_string()).unwrap_err();
+
+            assert_eq!(
+                error,
+                Error::SyntaxError(format!("Syntax error. Usage: {}", usage)),
+                "input {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn run_command_insert_with_default_email() {
+        let mut table = Table::new();
+
+        let error = run_command(&mut table, "insert 1 john".to_string()).unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::SyntaxError(
+                "Syntax error. Failed to parse 'email' from '' at position 13".to_string()
+            )
+        );
+
+        run_command(&mut table, ".defaultemail noreply@x.com".to_string()).unwrap();
+        run_command(&mut table, "insert 1 john".to_string()).unwrap();
+        run_command(&mut table, "insert 2 mary mary@mailbox.com".to_string()).unwrap();
+
+        let output = run_command(&mut table, "select".to_string()).unwrap();
+
+        assert_eq!(
+            output,
+            "(1, john, noreply@x.com)\n(2, mary, mary@mailbox.com)\nExecuted.\n"
+        );
+    }
+
+    #[test]
+    fn run_command_histogram() {
+        let mut table = Table::new();
+
+        let output = run_command(&mut table, ".histogram id 4".to_string());
+
+        assert_eq!(output, Ok("".to_string()));
+
+        for &id in &[1, 2, 3, 4, 5, 11, 12, 20] {
+            table.insert_values(id, "user", "user@mail.com").unwrap();
+        }
+
+        let output = run_command(&mut table, ".histogram id 4".to_string()).unwrap();
+
+        assert_eq!(
+            output,
+            "1-5   | ##### 5\n6-10  |       0\n11-15 | ##    2\n16-20 | #     1\n"
+        );
+
+        let output = run_command(&mut table, ".histogram rowid 100".to_string()).unwrap();
+
+        assert_eq!(output.lines().count(), 8);
+
+        let error = run_command(&mut table, ".histogram username 4".to_string()).unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::SyntaxError(
+                "Syntax error. '.histogram' requires a numeric column, not 'username'".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn run_command_histogram_empty_bucket() {
+        let mut table = Table::new();
+
+        insert_users(&mut table, [1, 9]);
+
+        let output = run_command(&mut table, ".histogram id 3".to_string()).unwrap();
+
+        assert_eq!(output, "1-3 | # 1\n4-6 |   0\n7-9 | # 1\n");
+    }
+
+    #[test]
+    fn run_command_histogram_wide_span() {
+        let mut table = Table::new();
+
+        table.insert_values(0, "user", "user@mail.com").unwrap();
+        table
+            .insert_values(usize::MAX, "user", "user@mail.com")
+            .unwrap();
+
+        let error =
+            run_command(&mut table, ".histogram id 100000000000000".to_string()).unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::SyntaxError(
+                "Syntax error. '.histogram' allows at most 1000 buckets".to_string()
+            )
+        );
+
+        let output = run_command(&mut table, ".histogram id 1000".to_string()).unwrap();
+
+        assert_eq!(output.lines().count(), 1000);
+        assert!(output.starts_with("0-"));
+        assert!(output.ends_with(&format!("-{} | # 1\n", usize::MAX)));
+    }
+
+    #[test]
+    fn run_command_insert_truncate() {
+        let mut table = Table::new();
+        let username = "é".repeat(40);
+
+        let error = run_command(&mut table, format!("insert 1 {} a@x.com", username)).unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::SyntaxError("Syntax error. 'username' is longer than 32 characters".to_string())
+        );
+
+        run_command(&mut table, ".truncate on".to_string()).unwrap();
+        run_command(&mut table, format!("insert 1 {} a@x.com", username)).unwrap();
+
+        let output = run_command(&mut table, "select".to_string()).unwrap();
+
+        assert_eq!(
+            output,
+            format!("(1, {}, a@x.com)\nExecuted.\n", "é".repeat(32))
+        );
+    }
+
+    #[test]
+    fn run_command_select_since() {
+        let mut table = Table::new();
+
+        insert_users(&mut table, [9, 8, 7, 6]);
+
+        let output = run_command(&mut table, "select since 2".to_string()).unwrap();
+
+        assert_eq!(
+            output,
+            "(7, user7, user@mail.com)\n(6, user6, user@mail.com)\nExecuted.\n"
+        );
+
+        let output = run_command(&mut table, "select since 4".to_string()).unwrap();
+
+        assert_eq!(output, "Executed.\n");
+
+        let output = run_command(&mut table, "select since 0".to_string()).unwrap();
+
+        assert_eq!(output.lines().count(), 5);
+    }
+
+    #[test]
+    fn run_script_file_returns_exit_code() {
+        let path = env::temp_dir().join("scoolite_run_script_file_returns_exit_code.txt");
+        let path = path.to_str().unwrap();
+
+        fs::write(
+            path,
+            "insert 1 john john@mailbox.com\nselect\n.exit 3\nvacuum\n",
+        )
+        .unwrap();
+
+        assert_eq!(run_script_file(path), Ok(3));
+
+        fs::write(path, "insert 1 john john@mailbox.com\n").unwrap();
+
+        assert_eq!(run_script_file(path), Ok(0));
+
+        fs::write(path, "insert 1 john\n.exit 3\n").unwrap();
+
+        assert_eq!(
+            run_script_file(path),
+            Err(Error::SyntaxError(
+                "Syntax error. Failed to parse 'email' from '' at position 13".to_string()
+            ))
+        );
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn run_command_select_where_id_in() {
+        let mut table = Table::new();
+
+        insert_users(&mut table, 1..=4);
+
+        let output = run_command(
+            &mut table,
+            "select where id in (4, 1, 7, 3) limit 2".to_string(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            output,
+            "(1, user1, user@mail.com)\n(3, user3, user@mail.com)\nExecuted.\n"
+        );
+
+        let output = run_command(
+            &mut table,
+            "select where id in (4,2) order by id desc".to_string(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            output,
+            "(4, user4, user@mail.com)\n(2, user2, user@mail.com)\nExecuted.\n"
+        );
+
+        let output = run_command(&mut table, "select where id in ()".to_string()).unwrap();
+
+        assert_eq!(output, "Executed.\n");
+
+        let error =
+            run_command(&mut table, "select where username in (1)".to_string()).unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::SyntaxError(
+                "Syntax error. 'in' requires a numeric column, not 'username' at position 13"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn run_command_timeout() {
+        let mut table = Table::new();
+
+        table.add_validator(|_| {
+            thread::sleep(Duration::from_millis(20));
+            Ok(())
+        });
+
+        run_command(&mut table, ".timeout 5".to_string()).unwrap();
+
+        let error = run_command(&mut table, "insert 1 a a@b.com".to_string()).unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::Timeout("Timeout. The command took longer than 5 ms".to_string())
+        );
+
+        run_command(&mut table, ".timeout 0".to_string()).unwrap();
+
+        assert_eq!(table.timeout(), None);
+        assert_eq!(
+            run_command(&mut table, "insert 1 a a@b.com".to_string()).unwrap(),
+            "Executed.\n"
+        );
+    }
+
+    #[test]
+    fn run_command_select_ids() {
+        let mut table = Table::new();
+
+        insert_users(&mut table, 1..=4);
+
+        let output = run_command(&mut table, "select ids where id in (1, 3, 9)".to_string());
+
+        assert_eq!(output.unwrap(), "1\n3\nExecuted.\n");
+
+        let output = run_command(
+            &mut table,
+            "select ids order by id desc limit 2".to_string(),
+        );
+
+        assert_eq!(output.unwrap(), "4\n3\nExecuted.\n");
+
+        let error = run_command(&mut table, "select ids group by id".to_string()).unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::SyntaxError("Syntax error. 'ids' can't be used with 'group by'".to_string())
+        );
+    }
+
+    #[test]
+    fn run_command_select_escape() {
+        let mut table = Table::new();
+        let mut format = Format::new();
+
+        table
+            .insert_values(1, "john\ndoe", "john\t@mail.com")
+            .unwrap();
+
+        let output =
+            run_command_with_format(&mut table, &mut format, "select".to_string()).unwrap();
+
+        assert_eq!(output, "(1, john\ndoe, john\t@mail.com)\nExecuted.\n");
+
+        run_command_with_format(&mut table, &mut format, ".escape on".to_string()).unwrap();
+
+        let output =
+            run_command_with_format(&mut table, &mut format, "select".to_string()).unwrap();
+
+        assert_eq!(output, "(1, john\\ndoe, john\\t@mail.com)\nExecuted.\n");
+    }
+
+    #[test]
+    fn run_command_select_line_mode() {
+        let mut table = Table::new();
+        let mut format = Format::new();
+
+        table.insert_values(1, "john", "john@mail.com").unwrap();
+        table.insert_values(2, "mary", "mary@mail.com").unwrap();
+
+        run_command_with_format(&mut table, &mut format, ".mode line".to_string()).unwrap();
+
+        let output =
+            run_command_with_format(&mut table, &mut format, "select".to_string()).unwrap();
+
+        assert_eq!(
+            output,
+            "id = 1\nusername = john\nemail = john@mail.com\n\n\
+             id = 2\nusername = mary\nemail = mary@mail.com\nExecuted.\n"
+        );
+    }
+
+    #[test]
+    fn run_command_select_offset() {
+        let mut table = Table::new();
+
+        insert_users(&mut table, 1..=4);
+
+        for (input, expected) in &[
+            ("select ids offset 1 limit 2", "2\n3\nExecuted.\n"),
+            ("select ids order by id desc offset 3", "1\nExecuted.\n"),
+            ("select ids offset 9", "Executed.\n"),
+            (
+                "select username, count group by username limit 1 offset 2",
+                "(user3, 1)\nExecuted.\n",
+            ),
+        ] {
+            let output = run_command(&mut table, input.to_string()).unwrap();
+
+            assert_eq!(output, *expected, "input {}", input);
+        }
+
+        for (input, expected) in &[
+            (
+                "select limit -1",
+                "Syntax error. 'limit' can't be negative, got '-1' at position 13",
+            ),
+            (
+                "select offset -1",
+                "Syntax error. 'offset' can't be negative, got '-1' at position 14",
+            ),
+            (
+                "select offset x",
+                "Syntax error. Failed to parse 'offset' from 'x' at position 14",
+            ),
+        ] {
+            let error = run_command(&mut table, input.to_string()).unwrap_err();
+
+            assert_eq!(error, Error::SyntaxError(expected.to_string()));
+        }
+    }
+
+    #[test]
+    fn run_command_trailing_semicolon() {
+        let mut table = Table::new();
+
+        let output = run_command(&mut table, "insert 1 a a@b.com;".to_string()).unwrap();
+
+        assert_eq!(output, "Executed.\n");
+        assert_eq!(table.find_row(1).unwrap().email(), "a@b.com");
+
+        assert_eq!(
+            run_command(&mut table, "select;".to_string()),
+            run_command(&mut table, "select".to_string())
+        );
+        assert_eq!(
+            run_command(&mut table, "select limit 1 ; ".to_string()),
+            run_command(&mut table, "select limit 1".to_string())
+        );
+        assert_eq!(run_command(&mut table, ";".to_string()).unwrap(), "");
+        assert_eq!(table.count(), 1);
+
+        let error = run_command(&mut table, "select;;".to_string()).unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::UnrecognizedStatement("Unrecognized keyword at start of 'select;'".to_string())
+        );
+    }
+
+    #[test]
+    fn run_command_rowid() {
+        let mut table = Table::new();
+
+        insert_users(&mut table, [5, 2, 9]);
+
+        for &(id, rowid) in &[(5, "1\n"), (2, "2\n"), (9, "3\n")] {
+            let output = run_command(&mut table, format!(".rowid {}", id)).unwrap();
+
+            assert_eq!(output, rowid, "id {}", id);
+        }
+
+        let error = run_command(&mut table, ".rowid 7".to_string()).unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::SyntaxError("Syntax error. There's no row with id 7".to_string())
+        );
+    }
+
+    #[test]
+    fn run_command_select_where_not() {
+        let mut table = Table::new();
+
+        insert_users(&mut table, 1..=4);
+
+        for (input, expected) in &[
+            ("select ids where not id in (1, 3)", "2\n4\nExecuted.\n"),
+            ("select ids where not not id in (1, 3)", "1\n3\nExecuted.\n"),
+            ("select ids where not id > 2", "1\n2\nExecuted.\n"),
+            (
+                "select ids where not rowid in ()",
+                "1\n2\n3\n4\nExecuted.\n",
+            ),
+        ] {
+            let output = run_command(&mut table, input.to_string()).unwrap();
+
+            assert_eq!(output, *expected, "input {}", input);
+        }
+    }
+
+    #[test]
+    fn run_command_select_where_long_not_chain() {
+        let mut table = Table::new();
+
+        insert_users(&mut table, 1..=4);
+
+        let even = format!("select ids where {}id in (1)", "not ".repeat(5000));
+        let odd = format!("select ids where {}id in (1)", "not ".repeat(5001));
+
+        assert_eq!(
+            run_command(&mut table, even),
+            Ok("1\nExecuted.\n".to_string())
+        );
+        assert_eq!(
+            run_command(&mut table, odd),
+            Ok("2\n3\n4\nExecuted.\n".to_string())
+        );
+    }
+
+    #[test]
+    fn run_command_trailer_off() {
+        let mut table = Table::new();
+        let mut format = Format::new();
+
+        run_command_with_format(&mut table, &mut format, ".trailer off".to_string()).unwrap();
+
+        let output =
+            run_command_with_format(&mut table, &mut format, "insert 1 a a@b.com".to_string())
+                .unwrap();
+
+        assert_eq!(output, "");
+
+        let output =
+            run_command_with_format(&mut table, &mut format, "select".to_string()).unwrap();
+
+        assert_eq!(output, "(1, a, a@b.com)\n");
+
+        run_command_with_format(&mut table, &mut format, ".trailer on".to_string()).unwrap();
+
+        let output =
+            run_command_with_format(&mut table, &mut format, "select".to_string()).unwrap();
+
+        assert_eq!(output, "(1, a, a@b.com)\nExecuted.\n");
+    }
+
+    #[test]
+    fn run_command_delete_range() {
+        let mut table = Table::new();
+
+        insert_users(&mut table, [1500, 3, 999, 1000, 2]);
+
+        let output = run_command(&mut table, "delete where id < 1000".to_string()).unwrap();
+
+        assert_eq!(output, "3 rows deleted\nExecuted.\n");
+
+        let output = run_command(&mut table, "select ids".to_string()).unwrap();
+
+        assert_eq!(output, "1500\n1000\nExecuted.\n");
+
+        run_command(&mut table, ".undo".to_string()).unwrap();
+
+        let output = run_command(&mut table, "select ids".to_string()).unwrap();
+
+        assert_eq!(output, "1500\n3\n999\n1000\n2\nExecuted.\n");
+
+        let output = run_command(&mut table, ".rowid 2".to_string()).unwrap();
+
+        assert_eq!(output, "5\n");
+
+        run_command(&mut table, "delete where id >= 1000".to_string()).unwrap();
+
+        let output = run_command(&mut table, "select ids where id >= 1000".to_string()).unwrap();
+
+        assert_eq!(output, "Executed.\n");
+
+        run_command(&mut table, ".undo".to_string()).unwrap();
+
+        let output = run_command(&mut table, "select ids where id >= 1500".to_string()).unwrap();
+
+        assert_eq!(output, "1500\nExecuted.\n");
+
+        run_command(&mut table, ".readonly on".to_string()).unwrap();
+
+        let error = run_command(&mut table, "delete where id = 1500".to_string()).unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::ReadOnly("Read-only mode. 'delete' can't change the table".to_string())
+        );
+
+        let error = run_command(&mut table, "delete where username < 3".to_string()).unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::SyntaxError(
+                "Syntax error. '<' requires a numeric column, not 'username' at position 13"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn run_command_validate() {
+        let mut table = Table::new();
+        let mut format = Format::new();
+
+        for input in &[
+            ".validate select where id > 5",
+            ".validate insert 1 john john@mailbox.com",
+            ".validate delete where id < 3",
+            ".validate .mode line",
+        ] {
+            let output =
+                run_command_with_format(&mut table, &mut format, input.to_string()).unwrap();
+
+            assert_eq!(output, "OK\n", "input {}", input);
+        }
+
+        for (input, expected) in &[
+            (
+                ".validate select where id >",
+                Error::SyntaxError(
+                    "Syntax error. Failed to parse '>' from '' at position 17".to_string(),
+                ),
+            ),
+            (
+                ".validate insert 1 john",
+                Error::SyntaxError(
+                    "Syntax error. Failed to parse 'email' from '' at position 13".to_string(),
+                ),
+            ),
+            (
+                ".validate .mode bogus",
+                Error::SyntaxError("Syntax error. Unknown mode 'bogus'".to_string()),
+            ),
+        ] {
+            let error =
+                run_command_with_format(&mut table, &mut format, input.to_string()).unwrap_err();
+
+            assert_eq!(error, *expected, "input {}", input);
+        }
+
+        assert_eq!(table.count(), 0);
+        assert_eq!(format.mode, Mode::List);
+    }
+
+    #[test]
+    fn run_command_validate_custom_command() {
+        let mut table = Table::new();
+        let mut registry = CommandRegistry::new();
+        registry.register_command("ping", |_input, _table| Ok("pong".to_string()));
+
+        assert_eq!(
+            registry.run_command(&mut table, ".validate ping".to_string()),
+            Ok("OK\n".to_string())
+        );
+        assert!(run_command(&mut table, ".validate ping".to_string()).is_err());
+    }
+
+    #[test]
+    fn run_command_select_count() {
+        let mut table = Table::new();
+        let mut format = Format::new();
+
+        let output =
+            run_command_with_format(&mut table, &mut format, "select count".to_string()).unwrap();
+
+        assert_eq!(output, "(0)\nExecuted.\n");
+
+        insert_users(&mut table, [1, 2, 3, 2]);
+
+        for (input, expected) in &[
+            ("select count", "(4)\nExecuted.\n"),
+            ("select count where id in (2, 3)", "(3)\nExecuted.\n"),
+            ("delete where id = 2", "2 rows deleted\nExecuted.\n"),
+            ("select count", "(2)\nExecuted.\n"),
+            (".undo", ""),
+            ("select count", "(4)\nExecuted.\n"),
+            ("insert 9 user9 user@mail.com", "Executed.\n"),
+            ("select count where not id in (9)", "(4)\nExecuted.\n"),
+            ("select count", "(5)\nExecuted.\n"),
+            ("select count limit 0", "(0)\nExecuted.\n"),
+            ("select count offset 6", "(0)\nExecuted.\n"),
+            ("select count offset 1 limit 3", "(3)\nExecuted.\n"),
+            (
+                "select count where id in (2, 3) limit 2",
+                "(2)\nExecuted.\n",
+            ),
+            (".dedup on", ""),
+            ("select count", "(4)\nExecuted.\n"),
+        ] {
+            let output =
+                run_command_with_format(&mut table, &mut format, input.to_string()).unwrap();
+
+            assert_eq!(output, *expected, "input {}", input);
+        }
+    }
 }
@@ -0,0 +1,222 @@
+use std::sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::command::{build_command, execute_command, execute_shared_command, CommandRegistry};
+use crate::error::Error;
+use crate::format::Format;
+use crate::table::Table;
+
+/// `Database` is a `Table` that can be shared across threads, usually
+/// behind an `Arc`. Readers proceed concurrently, while a writer has
+/// the `Table` all to itself.
+///
+/// Here's an example:
+/// ```
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// use scoolite::database::Database;
+///
+/// let database = Arc::new(Database::new());
+/// database.run_command("insert 1 john john@mailbox.com".to_string()).unwrap();
+///
+/// let reader = Arc::clone(&database);
+/// let count = thread::spawn(move || reader.reader().count()).join().unwrap();
+///
+/// assert_eq!(count, 1);
+/// ```
+pub struct Database {
+    table: RwLock<Table>,
+}
+
+impl Database {
+    /// Creates a `Database` with an empty `Table`.
+    pub fn new() -> Self {
+        Database {
+            table: RwLock::new(Table::new()),
+        }
+    }
+
+    /// Creates a `Database` with a `Table::new_readonly`, which no command can change.
+    pub fn new_readonly() -> Self {
+        Database {
+            table: RwLock::new(Table::new_readonly()),
+        }
+    }
+
+    /// Waits until there's no writer, then gives shared access to the `Table`.
+    ///
+    /// A writer that panicked doesn't make the `Database` unusable, since
+    /// every change to the `Table` is applied at once, the `Table` is
+    /// still handed out as it was left.
+    pub fn reader(&self) -> RwLockReadGuard<'_, Table> {
+        self.table.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Waits until there are no readers nor writers, then gives
+    /// exclusive access to the `Table`.
+    pub fn writer(&self) -> RwLockWriteGuard<'_, Table> {
+        self.table.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Runs a command like `run_command` does. The ones that only read
+    /// the `Table`, like a `select`, take the reader, so that many of them
+    /// run at once, while the rest take the writer.
+    pub fn run_command(&self, command: String) -> Result<String, Error> {
+        let command = build_command(&command, &CommandRegistry::new())?;
+        let mut format = Format::new();
+
+        if let Some(result) = execute_shared_command(command.as_ref(), &self.reader(), &mut format)
+        {
+            return result;
+        }
+
+        execute_command(command.as_ref(), &mut self.writer(), &mut format)
+    }
+}
+
+impl Default for Database {
+    fn default() -> Self {
+        Database::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::mpsc;
+    use std::sync::{Arc, Barrier};
+    use std::thread;
+    use std::time::Duration;
+
+    use crate::database::Database;
+    use crate::error::Error;
+    use crate::table::TableExt;
+
+    #[test]
+    fn concurrent_readers_and_writers() {
+        let database = Arc::new(Database::new());
+
+        let writers: Vec<_> = (0..4)
+            .map(|writer| {
+                let database = Arc::clone(&database);
+
+                thread::spawn(move || {
+                    for i in 0..100 {
+                        database
+                            .run_command(format!("insert {} user user@mail.com", writer * 100 + i))
+                            .unwrap();
+                    }
+                })
+            })
+            .collect();
+
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let database = Arc::clone(&database);
+
+                thread::spawn(move || {
+                    let mut last_count = 0;
+
+                    for _ in 0..100 {
+                        let count = database.reader().count();
+                        assert!(count >= last_count);
+                        last_count = count;
+                    }
+                })
+            })
+            .collect();
+
+        for handle in writers.into_iter().chain(readers) {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(database.reader().count(), 400);
+    }
+
+    #[test]
+    fn writer_is_exclusive() {
+        let database = Arc::new(Database::new());
+        let mut writer = database.writer();
+
+        let reader = {
+            let database = Arc::clone(&database);
+            thread::spawn(move || database.reader().count())
+        };
+
+        writer.insert_values(1, "john", "john@mailbox.com").unwrap();
+        writer.insert_values(2, "mary", "mary@mailbox.com").unwrap();
+        drop(writer);
+
+        assert_eq!(reader.join().unwrap(), 2);
+    }
+
+    #[test]
+    fn readers_hold_the_lock_at_once() {
+        let database = Arc::new(Database::new());
+        database
+            .run_command("insert 1 john john@mailbox.com".to_string())
+            .unwrap();
+
+        let barrier = Arc::new(Barrier::new(2));
+        let (sender, receiver) = mpsc::channel();
+
+        for _ in 0..2 {
+            let database = Arc::clone(&database);
+            let barrier = Arc::clone(&barrier);
+            let sender = sender.clone();
+
+            thread::spawn(move || {
+                let reader = database.reader();
+                barrier.wait();
+                sender.send(reader.count()).unwrap();
+            });
+        }
+
+        for _ in 0..2 {
+            let count = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+            assert_eq!(count, 1);
+        }
+
+        let _reader = database.reader();
+        let (sender, receiver) = mpsc::channel();
+        let selecting = Arc::clone(&database);
+
+        thread::spawn(move || {
+            sender
+                .send(selecting.run_command("select".to_string()))
+                .unwrap();
+        });
+
+        assert_eq!(
+            receiver.recv_timeout(Duration::from_secs(5)).unwrap(),
+            Ok("(1, john, john@mailbox.com)\nExecuted.\n".to_string())
+        );
+    }
+
+    #[test]
+    fn poisoned_lock_is_recovered() {
+        let database = Arc::new(Database::new());
+
+        let panicking = Arc::clone(&database);
+        let result = thread::spawn(move || {
+            let _writer = panicking.writer();
+            panic!("writer panicked");
+        })
+        .join();
+
+        assert!(result.is_err());
+        assert_eq!(database.reader().count(), 0);
+    }
+
+    #[test]
+    fn new_readonly_rejects_every_change() {
+        let database = Database::new_readonly();
+
+        for command in &["insert 1 john john@mailbox.com", ".readonly off"] {
+            let error = database.run_command(command.to_string()).unwrap_err();
+
+            assert!(matches!(error, Error::ReadOnly(_)), "command {}", command);
+        }
+
+        assert_eq!(database.reader().count(), 0);
+    }
+}
@@ -5,17 +5,56 @@ use std::fmt;
 #[derive(Debug, Clone, PartialEq)]
 pub enum Error {
     UnrecognizedStatement(String),
+    UnrecognizedCommand(String),
     SyntaxError(String),
+    Io(String),
+    ReadOnly(String),
+    /// Returned by the validators that embedders add to a `Table`.
+    Custom(String),
+    /// The command took longer than the `.timeout` of the `Table`.
+    Timeout(String),
+    /// A value computed for a `Row`, like `id + 1`, doesn't fit in its type.
+    OutOfRange(String),
+}
+
+/// How serious an `Error` is, so that hosts embedding `scoolite`
+/// can decide how to surface it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    /// The command was wrong, but nothing else is affected.
+    Warning,
+    /// The command failed while doing its work.
+    Error,
 }
 
 impl Error {
+    /// Classifies each variant of the `Error` type into a `Severity`.
+    pub fn severity(&self) -> Severity {
+        match self {
+            Error::UnrecognizedStatement(_) => Severity::Warning,
+            Error::UnrecognizedCommand(_) => Severity::Warning,
+            Error::SyntaxError(_) => Severity::Warning,
+            Error::Io(_) => Severity::Error,
+            Error::ReadOnly(_) => Severity::Warning,
+            Error::Custom(_) => Severity::Error,
+            Error::Timeout(_) => Severity::Error,
+            Error::OutOfRange(_) => Severity::Error,
+        }
+    }
+
     /// This function just get's the description depending of
     /// each variant of the `Error` type, usually the first
     /// field.
     fn get_description(&self) -> &str {
         match self {
             Error::UnrecognizedStatement(description) => description,
+            Error::UnrecognizedCommand(description) => description,
             Error::SyntaxError(description) => description,
+            Error::Io(description) => description,
+            Error::ReadOnly(description) => description,
+            Error::Custom(description) => description,
+            Error::Timeout(description) => description,
+            Error::OutOfRange(description) => description,
         }
     }
 }
@@ -33,3 +72,32 @@ impl error::Error for Error {
         self.get_description()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::error::{Error, Severity};
+
+    #[test]
+    fn severity_of_each_variant() {
+        let expected_severities = [
+            (
+                Error::UnrecognizedStatement("message".to_string()),
+                Severity::Warning,
+            ),
+            (
+                Error::UnrecognizedCommand("message".to_string()),
+                Severity::Warning,
+            ),
+            (Error::SyntaxError("message".to_string()), Severity::Warning),
+            (Error::Io("message".to_string()), Severity::Error),
+            (Error::ReadOnly("message".to_string()), Severity::Warning),
+            (Error::Custom("message".to_string()), Severity::Error),
+            (Error::Timeout("message".to_string()), Severity::Error),
+            (Error::OutOfRange("message".to_string()), Severity::Error),
+        ];
+
+        for (error, expected_severity) in &expected_severities {
+            assert_eq!(error.severity(), *expected_severity, "error {:?}", error);
+        }
+    }
+}
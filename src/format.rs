@@ -0,0 +1,194 @@
+use std::borrow::Cow;
+use std::str::FromStr;
+
+use crate::error::Error;
+use crate::row::Row;
+
+/// The maximum `id_width`, wider ids are still written, just not padded.
+pub const ID_WIDTH_MAX: usize = 64;
+
+/// `Format` holds the settings of how the output of a `select` looks,
+/// they are changed by meta commands like `.rowsep`, `.mode`, `.format`
+/// and `.numberformat`. It belongs to a session, like a `Repl`, not to
+/// the `Table`, so that each one can show the same `Row`s its own way.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Format {
+    pub mode: Mode,
+    pub row_separator: String,
+    /// Minimum width of the `id` in `list` mode, smaller
+    /// ids are aligned to the right. `0` means no padding.
+    pub id_width: usize,
+    pub number_format: NumberFormat,
+    /// Collapses `Row`s with the same `id`, keeping the last one inserted.
+    pub dedup: bool,
+    /// Maximum amount of bytes of the `Row`s of a `select`, the
+    /// following ones are left out. `0` means unlimited.
+    pub max_output: usize,
+    /// Writes the control characters of text values in `list` and `line`
+    /// modes escaped, like `\n`, so they can't garble the output.
+    pub escape: bool,
+    /// Ends the output of every statement with `Executed.`.
+    pub trailer: bool,
+}
+
+impl Format {
+    /// Creates the default `Format`, one row per line in `list` mode.
+    pub fn new() -> Self {
+        Format {
+            mode: Mode::List,
+            row_separator: "\n".to_string(),
+            id_width: 0,
+            number_format: NumberFormat::Decimal,
+            dedup: false,
+            max_output: 0,
+            escape: false,
+            trailer: true,
+        }
+    }
+
+    /// Formats a single `Row` depending on the `mode`.
+    pub fn format_row(&self, row: &Row) -> String {
+        match self.mode {
+            Mode::List => format!(
+                "({:>width$}, {}, {})",
+                self.number_format.format(row.id()),
+                self.format_text(row.username()),
+                self.format_text(row.email()),
+                width = self.id_width
+            ),
+            Mode::Insert => format!("insert {} {} {}", row.id(), row.username(), row.email()),
+            Mode::Line => format!(
+                "id = {}\nusername = {}\nemail = {}",
+                self.number_format.format(row.id()),
+                self.format_text(row.username()),
+                self.format_text(row.email())
+            ),
+        }
+    }
+
+    /// Returns the text `value` escaped if `escape` is on, otherwise as it is.
+    fn format_text<'a>(&self, value: &'a str) -> Cow<'a, str> {
+        if !self.escape || !value.chars().any(char::is_control) {
+            return Cow::Borrowed(value);
+        }
+
+        value
+            .chars()
+            .map(|character| match character {
+                character if character.is_control() => character.escape_default().to_string(),
+                character => character.to_string(),
+            })
+            .collect()
+    }
+
+    /// Joins the formatted `lines` of a `select` with the `row_separator`,
+    /// ending with a new line if there's any. In `line` mode each `Row`
+    /// takes many lines, so they're separated by a blank line instead.
+    ///
+    /// Once the next line would go over `max_output` bytes, it stops
+    /// pulling `lines` and ends with the `-- output truncated` marker.
+    pub fn join_lines<I>(&self, lines: I) -> String
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut output = String::new();
+
+        for (index, line) in lines.into_iter().enumerate() {
+            let separator = match (index, self.mode) {
+                (0, _) => "",
+                (_, Mode::Line) => "\n\n",
+                _ => &self.row_separator,
+            };
+
+            if self.max_output > 0 && output.len() + separator.len() + line.len() > self.max_output
+            {
+                if index > 0 {
+                    output.push('\n');
+                }
+
+                output.push_str("-- output truncated");
+                break;
+            }
+
+            output.push_str(separator);
+            output.push_str(&line);
+        }
+
+        if output.is_empty() {
+            return output;
+        }
+
+        output.push('\n');
+        output
+    }
+}
+
+impl Default for Format {
+    fn default() -> Self {
+        Format::new()
+    }
+}
+
+/// The display modes for the `Row`s of a `select`, set by `.mode <name>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Mode {
+    /// `(1, john, john@mailbox.com)`
+    List,
+    /// `insert 1 john john@mailbox.com`, so the output can be
+    /// run again on another `scoolite` instance.
+    Insert,
+    /// `id = 1`, `username = john` and `email = john@mailbox.com`
+    /// each on its own line.
+    Line,
+}
+
+/// How the `id`s are written in `list` mode, set by `.numberformat <name>`.
+/// The `insert` mode always uses decimal, since that's what `insert` parses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NumberFormat {
+    /// `31`
+    Decimal,
+    /// `0x1f`
+    Hex,
+}
+
+impl NumberFormat {
+    /// Writes the `number` in this format.
+    pub fn format(&self, number: usize) -> String {
+        match self {
+            NumberFormat::Decimal => number.to_string(),
+            NumberFormat::Hex => format!("{:#x}", number),
+        }
+    }
+}
+
+impl FromStr for NumberFormat {
+    type Err = Error;
+
+    fn from_str(input: &str) -> Result<Self, Error> {
+        match input {
+            "dec" => Ok(NumberFormat::Decimal),
+            "hex" => Ok(NumberFormat::Hex),
+            _ => Err(Error::SyntaxError(format!(
+                "Syntax error. Unknown number format '{}'",
+                input
+            ))),
+        }
+    }
+}
+
+impl FromStr for Mode {
+    type Err = Error;
+
+    fn from_str(input: &str) -> Result<Self, Error> {
+        match input {
+            "list" => Ok(Mode::List),
+            "insert" => Ok(Mode::Insert),
+            "line" => Ok(Mode::Line),
+            _ => Err(Error::SyntaxError(format!(
+                "Syntax error. Unknown mode '{}'",
+                input
+            ))),
+        }
+    }
+}
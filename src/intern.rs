@@ -0,0 +1,94 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// `Interner` keeps a single copy of each text value, so that `Row`s with
+/// the same `username` or `email` can share it instead of each having theirs.
+#[derive(Debug, Default)]
+pub struct Interner {
+    strings: HashSet<Arc<str>>,
+    lookups: usize,
+    hits: usize,
+}
+
+impl Interner {
+    /// Creates an `Interner` without any text.
+    pub fn new() -> Self {
+        Interner::default()
+    }
+
+    /// Returns the shared copy of `text`, storing it first if it's new.
+    pub fn intern(&mut self, text: &Arc<str>) -> Arc<str> {
+        self.lookups += 1;
+
+        if let Some(shared) = self.strings.get(text) {
+            self.hits += 1;
+            return Arc::clone(shared);
+        }
+
+        self.strings.insert(Arc::clone(text));
+
+        Arc::clone(text)
+    }
+
+    /// Forgets the values that no `Row` uses anymore,
+    /// the ones whose only copy is the one stored here.
+    pub fn prune(&mut self) {
+        self.strings.retain(|text| Arc::strong_count(text) > 1);
+    }
+
+    /// Returns the stored values, to estimate how much memory they use.
+    pub(crate) fn values(&self) -> impl Iterator<Item = &Arc<str>> {
+        self.strings.iter()
+    }
+
+    /// Returns how many values can be stored without allocating again.
+    pub(crate) fn capacity(&self) -> usize {
+        self.strings.capacity()
+    }
+
+    /// Returns which fraction of the interned values were already stored,
+    /// from `0.0`, when every value is different, up to almost `1.0`.
+    pub fn dedup_ratio(&self) -> f64 {
+        if self.lookups == 0 {
+            return 0.0;
+        }
+
+        self.hits as f64 / self.lookups as f64
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use crate::intern::Interner;
+
+    #[test]
+    fn intern_shares_equal_values() {
+        let mut interner = Interner::new();
+
+        let first = interner.intern(&Arc::from("john"));
+        let second = interner.intern(&Arc::from("john"));
+        let other = interner.intern(&Arc::from("mary"));
+
+        assert!(Arc::ptr_eq(&first, &second));
+        assert!(!Arc::ptr_eq(&first, &other));
+        assert_eq!(&*second, "john");
+        assert!((interner.dedup_ratio() - 1.0 / 3.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn prune_forgets_unused_values() {
+        let mut interner = Interner::new();
+
+        let kept = interner.intern(&Arc::from("john"));
+        drop(interner.intern(&Arc::from("mary")));
+
+        interner.prune();
+
+        let values: Vec<&str> = interner.values().map(|value| &**value).collect();
+
+        assert_eq!(values, vec!["john"]);
+        assert!(Arc::ptr_eq(&kept, &interner.intern(&Arc::from("john"))));
+    }
+}
@@ -20,7 +20,7 @@ pub fn read_input() -> String {
     input
 }
 
-/// Just prints an `Error`'s `description` to the terminal.
+/// Just prints an `Error`'s message to the terminal.
 pub fn print_error<E: error::Error>(error: E) {
-    println!("{}", error.description());
+    println!("{}", error);
 }
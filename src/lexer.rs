@@ -0,0 +1,282 @@
+use std::fmt;
+
+use crate::error::Error;
+
+/// The kinds of `Token`s, words are anything between whitespace
+/// and punctuation, like `insert`, `1` or `john@mailbox.com`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TokenKind {
+    Word,
+    Comma,
+    OpenParen,
+    CloseParen,
+}
+
+impl fmt::Display for TokenKind {
+    /// Writes how the `Token` looks in the input, words are just `word`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match self {
+            TokenKind::Word => "word",
+            TokenKind::Comma => ",",
+            TokenKind::OpenParen => "(",
+            TokenKind::CloseParen => ")",
+        };
+
+        write!(f, "{}", text)
+    }
+}
+
+/// A piece of the input, with the byte `position` where it starts,
+/// so that errors can point to it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Token<'a> {
+    pub kind: TokenKind,
+    pub text: &'a str,
+    pub position: usize,
+}
+
+/// `Lexer` splits an input into `Token`s, and it's used as an `Iterator`
+/// over them by the parsers of `scoolite`.
+///
+/// Here's an example:
+/// ```
+/// use scoolite::lexer::{Lexer, TokenKind};
+///
+/// let mut lexer = Lexer::new("select username, count");
+///
+/// assert_eq!(lexer.next().unwrap().text, "select");
+/// assert_eq!(lexer.next().unwrap().position, 7);
+/// assert_eq!(lexer.next().unwrap().kind, TokenKind::Comma);
+/// assert_eq!(lexer.next_position(), 17);
+/// ```
+pub struct Lexer<'a> {
+    tokens: Vec<Token<'a>>,
+    index: usize,
+    end: usize,
+}
+
+impl<'a> Lexer<'a> {
+    /// Tokenizes the whole `input` right away.
+    pub fn new(input: &'a str) -> Self {
+        Lexer {
+            tokens: tokenize(input),
+            index: 0,
+            end: input.len(),
+        }
+    }
+
+    /// Returns the next `Token` without consuming it.
+    pub fn peek(&self) -> Option<&Token<'a>> {
+        self.tokens.get(self.index)
+    }
+
+    /// Consumes the next `Token` as a number, `name` is only used on the error message.
+    pub fn next_number(&mut self, name: &str) -> Result<usize, Error> {
+        let position = self.next_position();
+        let text = self.next().map(|token| token.text).unwrap_or("");
+
+        if text.strip_prefix('-').and_then(parse_number).is_some() {
+            return Err(at_position(
+                Error::SyntaxError(format!(
+                    "Syntax error. '{}' can't be negative, got '{}'",
+                    name, text
+                )),
+                position,
+            ));
+        }
+
+        parse_number(text).ok_or_else(|| {
+            at_position(
+                Error::SyntaxError(format!(
+                    "Syntax error. Failed to parse '{}' from '{}'",
+                    name, text
+                )),
+                position,
+            )
+        })
+    }
+
+    /// Consumes the next `Token`, returning a `SyntaxError` if it isn't of `kind`.
+    pub fn expect(&mut self, kind: TokenKind) -> Result<(), Error> {
+        let position = self.next_position();
+
+        match self.next() {
+            Some(token) if token.kind == kind => Ok(()),
+            _ => Err(at_position(
+                Error::SyntaxError(format!("Syntax error. Expected '{}'", kind)),
+                position,
+            )),
+        }
+    }
+
+    /// Returns a `SyntaxError` if there are any `Token`s left.
+    pub fn expect_end(&mut self) -> Result<(), Error> {
+        match self.next() {
+            Some(token) => Err(at_position(
+                Error::SyntaxError(format!("Syntax error. Unexpected '{}'", token.text)),
+                token.position,
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the position of the next `Token`, or the
+    /// end of the input if there are no more of them.
+    pub fn next_position(&self) -> usize {
+        self.peek().map(|token| token.position).unwrap_or(self.end)
+    }
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Token<'a>> {
+        let token = self.tokens.get(self.index).copied();
+
+        if token.is_some() {
+            self.index += 1;
+        }
+
+        token
+    }
+}
+
+fn tokenize(input: &str) -> Vec<Token<'_>> {
+    let mut tokens = vec![];
+    let mut word_start = None;
+
+    for (position, character) in input.char_indices() {
+        let kind = match character {
+            ',' => Some(TokenKind::Comma),
+            '(' => Some(TokenKind::OpenParen),
+            ')' => Some(TokenKind::CloseParen),
+            _ => None,
+        };
+
+        if kind.is_some() || character.is_whitespace() {
+            if let Some(start) = word_start.take() {
+                tokens.push(build_word(input, start, position));
+            }
+        } else if word_start.is_none() {
+            word_start = Some(position);
+        }
+
+        if let Some(kind) = kind {
+            tokens.push(Token {
+                kind,
+                text: &input[position..position + 1],
+                position,
+            });
+        }
+    }
+
+    if let Some(start) = word_start {
+        tokens.push(build_word(input, start, input.len()));
+    }
+
+    tokens
+}
+
+fn build_word(input: &str, start: usize, end: usize) -> Token<'_> {
+    Token {
+        kind: TokenKind::Word,
+        text: &input[start..end],
+        position: start,
+    }
+}
+
+/// Parses `text` as a `usize`, but unlike `str::parse` a leading `+`
+/// isn't accepted, the same way a leading `-` isn't.
+pub fn parse_number(text: &str) -> Option<usize> {
+    if text.starts_with('+') {
+        return None;
+    }
+
+    text.parse().ok()
+}
+
+/// Adds ` at position N` to the message of a `SyntaxError`,
+/// any other `Error` is returned as it is.
+pub fn at_position(error: Error, position: usize) -> Error {
+    match error {
+        Error::SyntaxError(message) => {
+            Error::SyntaxError(format!("{} at position {}", message, position))
+        }
+        error => error,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::lexer::{Lexer, Token, TokenKind};
+
+    #[test]
+    fn lexer_words_and_punctuation() {
+        let tokens: Vec<Token> = Lexer::new("select id,  email(x)").collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                Token {
+                    kind: TokenKind::Word,
+                    text: "select",
+                    position: 0
+                },
+                Token {
+                    kind: TokenKind::Word,
+                    text: "id",
+                    position: 7
+                },
+                Token {
+                    kind: TokenKind::Comma,
+                    text: ",",
+                    position: 9
+                },
+                Token {
+                    kind: TokenKind::Word,
+                    text: "email",
+                    position: 12
+                },
+                Token {
+                    kind: TokenKind::OpenParen,
+                    text: "(",
+                    position: 17
+                },
+                Token {
+                    kind: TokenKind::Word,
+                    text: "x",
+                    position: 18
+                },
+                Token {
+                    kind: TokenKind::CloseParen,
+                    text: ")",
+                    position: 19
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn lexer_position_at_end() {
+        let mut lexer = Lexer::new("insert   1");
+
+        assert_eq!(lexer.next_position(), 0);
+
+        lexer.next();
+
+        assert_eq!(lexer.next_position(), 9);
+
+        lexer.next();
+
+        assert_eq!(lexer.next_position(), 10);
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[test]
+    fn lexer_multibyte_positions() {
+        let tokens: Vec<Token> = Lexer::new("insert 1 josé é@mail.com").collect();
+
+        assert_eq!(tokens[2].text, "josé");
+        assert_eq!(tokens[3].position, 15);
+    }
+}
@@ -4,12 +4,33 @@ pub mod as_any;
 /// Module for `Command`s/`Statement`s of the SQL-like language
 pub mod command;
 
+/// Module for `Database` type, a `Table` shared across threads
+pub mod database;
+
 /// Module for `Error` type
 pub mod error;
 
+/// Module for `Format` type, how the output of a `select` looks
+pub mod format;
+
 /// Module for interacting with stdin and stdout
 pub mod io;
 
+/// Module for `Interner` type, that shares repeated text values
+pub mod intern;
+
+/// Module for `Lexer` type, that splits inputs into `Token`s
+pub mod lexer;
+
+/// Module for `Query` type, the clauses of a `select`
+pub mod query;
+
+/// Module for `Random` type, a seedable pseudo random number generator
+pub mod random;
+
+/// Module for `Repl` type, the state of an interactive session
+pub mod repl;
+
 /// Module for `Table` type
 pub mod table;
 
@@ -1,17 +1,16 @@
-use scoolite::command::run_command;
 use scoolite::io::{print_error, print_prompt, read_input};
-use scoolite::table::Table;
+use scoolite::repl::Repl;
 
 /// scoolite REPL implementation.
 fn main() {
-    let mut table = Table::new();
+    let mut repl = Repl::new();
 
     loop {
         print_prompt();
 
         let input = read_input();
 
-        match run_command(&mut table, input) {
+        match repl.run_command(input) {
             Ok(output) => print!("{}", output),
             Err(error) => print_error(error),
         }
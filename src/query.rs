@@ -0,0 +1,745 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::Error;
+use crate::lexer::{at_position, Lexer, Token, TokenKind};
+use crate::row::{Column, Row};
+
+/// `Query` holds the clauses that can follow a `select`,
+/// like `select order by username`.
+///
+/// An empty `projection` means that whole `Row`s are returned,
+/// otherwise each `Row` is returned as the values of its `projection`.
+/// With `ids`, from `select ids`, only the `id` of each `Row` is returned.
+#[derive(Debug, Default, PartialEq)]
+pub struct Query {
+    pub projection: Vec<Projection>,
+    pub ids: bool,
+    pub filter: Option<Filter>,
+    pub group_by: Option<Column>,
+    pub order_by: Option<OrderBy>,
+    pub limit: Option<usize>,
+    pub offset: Option<usize>,
+}
+
+impl FromStr for Query {
+    type Err = Error;
+
+    /// Parses an input like `select order by username collate nocase`
+    /// into a `Query`, if any clause is malformed it returns a `SyntaxError`.
+    fn from_str(input: &str) -> Result<Self, Error> {
+        let mut tokens = Lexer::new(input);
+        tokens.next();
+
+        let mut query = Query::default();
+
+        if tokens.peek().is_some_and(|token| token.text == "ids") {
+            tokens.next();
+            query.ids = true;
+        } else if tokens.peek().is_some_and(|token| !is_clause_keyword(token)) {
+            query.projection = parse_projection(&mut tokens)?;
+        }
+
+        while let Some(token) = tokens.next() {
+            match token.text {
+                "where" => query.filter = Some(Filter::parse(&mut tokens)?),
+                "group" => {
+                    expect_keyword(&mut tokens, "by")?;
+                    query.group_by = Some(parse_column(&mut tokens, "group by")?);
+                }
+                "order" => query.order_by = Some(OrderBy::parse(&mut tokens)?),
+                "limit" => query.limit = Some(tokens.next_number("limit")?),
+                "offset" => query.offset = Some(tokens.next_number("offset")?),
+                _ => return Err(build_unexpected_token_error(token)),
+            }
+        }
+
+        query.validate_projection()?;
+
+        Ok(query)
+    }
+}
+
+impl Query {
+    /// Returns if the `row` passes the `where` clause, if there's one.
+    pub fn matches(&self, row: &Row) -> bool {
+        self.filter
+            .as_ref()
+            .is_none_or(|filter| filter.matches(row))
+    }
+
+    /// Returns if it's a `select count`, that returns how many `Row`s match.
+    pub fn is_count(&self) -> bool {
+        self.group_by.is_none() && self.projection == [Projection::Count]
+    }
+
+    /// A `count` is only allowed alone or together with `group by`, and in
+    /// that case the other columns of the projection must be the grouped one.
+    fn validate_projection(&self) -> Result<(), Error> {
+        if self.ids && self.group_by.is_some() {
+            return Err(Error::SyntaxError(
+                "Syntax error. 'ids' can't be used with 'group by'".to_string(),
+            ));
+        }
+
+        let group_by = match self.group_by {
+            Some(group_by) => group_by,
+            None if self.projection.contains(&Projection::Count) && !self.is_count() => {
+                return Err(Error::SyntaxError(
+                    "Syntax error. 'count' requires a 'group by'".to_string(),
+                ))
+            }
+            None => return Ok(()),
+        };
+
+        if self.projection.is_empty() {
+            return Err(Error::SyntaxError(
+                "Syntax error. 'group by' requires selecting columns".to_string(),
+            ));
+        }
+
+        let columns = self
+            .projection
+            .iter()
+            .flat_map(|projection| match projection {
+                Projection::Column(column)
+                | Projection::Length(column)
+                | Projection::Arithmetic { column, .. } => vec![*column],
+                Projection::Concat(columns) => columns.clone(),
+                Projection::Count => vec![],
+            });
+
+        for column in columns {
+            if column != group_by {
+                return Err(Error::SyntaxError(format!(
+                    "Syntax error. Column '{}' must appear in 'group by'",
+                    column
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Sorts `rows` by the `order by` clause, skips the first `offset`
+    /// of them and keeps only the next `limit`, if there are those clauses.
+    ///
+    /// When the `offset` plus `limit` is small compared to the amount of `rows`,
+    /// instead of sorting all of them it keeps a bounded heap with the best
+    /// ones seen so far. Both ways return the same `Row`s in the same order.
+    pub fn order_and_limit<'a>(&self, mut rows: Vec<&'a Row>) -> Vec<&'a Row> {
+        let offset = self.offset.unwrap_or(0);
+
+        match (&self.order_by, self.limit) {
+            (Some(order_by), Some(limit))
+                if offset.saturating_add(limit) <= rows.len() / TOP_N_HEAP_RATIO =>
+            {
+                let mut rows = order_by.top_n(&rows, offset + limit);
+                rows.drain(..offset);
+                rows
+            }
+            (order_by, limit) => {
+                if let Some(order_by) = order_by {
+                    rows.sort_by(|a, b| order_by.compare(a, b));
+                }
+
+                rows.drain(..offset.min(rows.len()));
+
+                if let Some(limit) = limit {
+                    rows.truncate(limit);
+                }
+
+                rows
+            }
+        }
+    }
+
+    /// Buckets `rows` by the `group by` column, returning one line per
+    /// distinct value with the `projection` applied, like `(john, 2)`.
+    /// Groups are returned in the order they were first seen.
+    pub fn group(&self, group_by: Column, rows: &[&Row]) -> Result<Vec<String>, Error> {
+        let mut positions: HashMap<String, usize> = HashMap::new();
+        let mut groups: Vec<(&Row, usize)> = vec![];
+
+        for row in rows {
+            let value = row.get(group_by);
+
+            match positions.get(&value) {
+                Some(&position) => groups[position].1 += 1,
+                None => {
+                    positions.insert(value, groups.len());
+                    groups.push((row, 1));
+                }
+            }
+        }
+
+        groups
+            .iter()
+            .map(|(row, count)| self.project(row, *count))
+            .collect()
+    }
+
+    /// Formats the values of the `projection` for a `Row`, like `(1, 4)`,
+    /// `count` is the size of the group the `Row` stands for.
+    pub fn project(&self, row: &Row, count: usize) -> Result<String, Error> {
+        let values = self
+            .projection
+            .iter()
+            .map(|projection| projection.evaluate(row, count))
+            .collect::<Result<Vec<String>, Error>>()?;
+
+        Ok(format!("({})", values.join(", ")))
+    }
+}
+
+/// `Filter` represents the `where` clause, only the `Row`s that match it are used.
+#[derive(Debug, PartialEq)]
+pub enum Filter {
+    /// `where <column> in (1, 3, 5)`, for numeric columns.
+    In(Column, HashSet<usize>),
+    /// `where <column> < 1000`, for numeric columns.
+    Compare(Column, Comparison, usize),
+    /// `where not <filter>`, the `Row`s that don't match the inner `Filter`.
+    Not(Box<Filter>),
+}
+
+impl Filter {
+    /// Parses what comes after the `where` keyword, a chain of `not`s
+    /// is folded into at most one `Filter::Not`.
+    pub(crate) fn parse(tokens: &mut Lexer) -> Result<Self, Error> {
+        let mut negated = false;
+
+        while tokens.peek().is_some_and(|token| token.text == "not") {
+            tokens.next();
+            negated = !negated;
+        }
+
+        let filter = Filter::parse_condition(tokens)?;
+
+        if negated {
+            Ok(Filter::Not(Box::new(filter)))
+        } else {
+            Ok(filter)
+        }
+    }
+
+    /// Parses a single condition, like `id in (1, 2)` or `id < 5`.
+    fn parse_condition(tokens: &mut Lexer) -> Result<Self, Error> {
+        let position = tokens.next_position();
+        let column = parse_column(tokens, "where")?;
+        let operator_position = tokens.next_position();
+        let operator = tokens.next().map(|token| token.text).unwrap_or("");
+        let comparison = operator.parse::<Comparison>().ok();
+
+        if operator != "in" && comparison.is_none() {
+            return Err(at_position(
+                Error::SyntaxError("Syntax error. Expected 'in' or a comparison".to_string()),
+                operator_position,
+            ));
+        }
+
+        if column != Column::Id && column != Column::RowId {
+            return Err(at_position(
+                Error::SyntaxError(format!(
+                    "Syntax error. '{}' requires a numeric column, not '{}'",
+                    operator, column
+                )),
+                position,
+            ));
+        }
+
+        if let Some(comparison) = comparison {
+            let value = tokens.next_number(operator)?;
+
+            return Ok(Filter::Compare(column, comparison, value));
+        }
+
+        tokens.expect(TokenKind::OpenParen)?;
+
+        let mut values = HashSet::new();
+
+        if tokens.peek().map(|token| token.kind) != Some(TokenKind::CloseParen) {
+            values.insert(tokens.next_number("in")?);
+
+            while tokens.peek().map(|token| token.kind) == Some(TokenKind::Comma) {
+                tokens.next();
+                values.insert(tokens.next_number("in")?);
+            }
+        }
+
+        tokens.expect(TokenKind::CloseParen)?;
+
+        Ok(Filter::In(column, values))
+    }
+
+    /// Returns if the `row` matches this `Filter`.
+    pub fn matches(&self, row: &Row) -> bool {
+        match self {
+            Filter::In(column, values) => row
+                .get_number(*column)
+                .is_some_and(|value| values.contains(&value)),
+            Filter::Compare(column, comparison, value) => row
+                .get_number(*column)
+                .is_some_and(|number| comparison.compare(number, *value)),
+            Filter::Not(filter) => !filter.matches(row),
+        }
+    }
+}
+
+/// The operators of `Filter::Compare`, like `<` or `>=`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Equal,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl Comparison {
+    /// Returns if `left` and `right` are related by this `Comparison`.
+    pub fn compare(&self, left: usize, right: usize) -> bool {
+        match self {
+            Comparison::Equal => left == right,
+            Comparison::Less => left < right,
+            Comparison::LessOrEqual => left <= right,
+            Comparison::Greater => left > right,
+            Comparison::GreaterOrEqual => left >= right,
+        }
+    }
+}
+
+impl FromStr for Comparison {
+    type Err = Error;
+
+    fn from_str(input: &str) -> Result<Self, Error> {
+        match input {
+            "=" => Ok(Comparison::Equal),
+            "<" => Ok(Comparison::Less),
+            "<=" => Ok(Comparison::LessOrEqual),
+            ">" => Ok(Comparison::Greater),
+            ">=" => Ok(Comparison::GreaterOrEqual),
+            _ => Err(Error::SyntaxError(format!(
+                "Syntax error. Unknown comparison '{}'",
+                input
+            ))),
+        }
+    }
+}
+
+/// Each item of the list between `select` and the clauses,
+/// like `username, count`.
+#[derive(Debug, PartialEq)]
+pub enum Projection {
+    Column(Column),
+    Count,
+    /// The amount of characters of a text column, like `length(username)`.
+    Length(Column),
+    /// The values of text columns joined together, like `concat(username, email)`.
+    Concat(Vec<Column>),
+    /// A numeric column plus or minus a number, like `id + 1000`.
+    Arithmetic {
+        column: Column,
+        operator: Operator,
+        operand: usize,
+    },
+}
+
+/// The operators of a `Projection::Arithmetic`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operator {
+    Add,
+    Subtract,
+}
+
+impl fmt::Display for Operator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Operator::Add => write!(f, "+"),
+            Operator::Subtract => write!(f, "-"),
+        }
+    }
+}
+
+impl Projection {
+    /// Parses a single item of the projection list.
+    fn parse(tokens: &mut Lexer) -> Result<Self, Error> {
+        let position = tokens.next_position();
+
+        match tokens.next() {
+            Some(token) if token.text == "count" => Ok(Projection::Count),
+            Some(token) if token.text == "length" => {
+                tokens.expect(TokenKind::OpenParen)?;
+                let column = parse_text_column(tokens, "length")?;
+                tokens.expect(TokenKind::CloseParen)?;
+
+                Ok(Projection::Length(column))
+            }
+            Some(token) if token.text == "concat" => {
+                tokens.expect(TokenKind::OpenParen)?;
+                let mut columns = vec![parse_text_column(tokens, "concat")?];
+
+                while tokens.peek().map(|token| token.kind) == Some(TokenKind::Comma) {
+                    tokens.next();
+                    columns.push(parse_text_column(tokens, "concat")?);
+                }
+
+                tokens.expect(TokenKind::CloseParen)?;
+
+                Ok(Projection::Concat(columns))
+            }
+            Some(token) => {
+                let column = parse_column_token(token)?;
+
+                let operator = match tokens.peek().map(|token| token.text) {
+                    Some("+") => Operator::Add,
+                    Some("-") => Operator::Subtract,
+                    _ => return Ok(Projection::Column(column)),
+                };
+
+                if column != Column::Id && column != Column::RowId {
+                    return Err(at_position(
+                        Error::SyntaxError(format!(
+                            "Syntax error. '{}' requires a numeric column, not '{}'",
+                            operator, column
+                        )),
+                        token.position,
+                    ));
+                }
+
+                tokens.next();
+                let operand = tokens.next_number(&operator.to_string())?;
+
+                Ok(Projection::Arithmetic {
+                    column,
+                    operator,
+                    operand,
+                })
+            }
+            None => Err(at_position(
+                Error::SyntaxError("Syntax error. Expected a column".to_string()),
+                position,
+            )),
+        }
+    }
+
+    /// Computes the value of this item for a `Row`, `count` is
+    /// the size of the group the `Row` stands for.
+    ///
+    /// Arithmetic never wraps around, if the result doesn't fit
+    /// in an `id` it returns an `OutOfRange` error instead.
+    fn evaluate(&self, row: &Row, count: usize) -> Result<String, Error> {
+        let value = match self {
+            Projection::Column(column) => row.get(*column),
+            Projection::Count => count.to_string(),
+            Projection::Length(column) => row.get(*column).chars().count().to_string(),
+            Projection::Concat(columns) => columns.iter().map(|column| row.get(*column)).collect(),
+            Projection::Arithmetic {
+                column,
+                operator,
+                operand,
+            } => {
+                let value = row.get_number(*column).unwrap_or_default();
+
+                let result = match operator {
+                    Operator::Add => value.checked_add(*operand),
+                    Operator::Subtract => value.checked_sub(*operand),
+                };
+
+                result.map(|result| result.to_string()).ok_or_else(|| {
+                    Error::OutOfRange(format!(
+                        "Out of range. '{} {} {}' overflows for {} {}",
+                        column, operator, operand, column, value
+                    ))
+                })?
+            }
+        };
+
+        Ok(value)
+    }
+}
+
+/// Parses the comma separated projection list, like `username, count`.
+fn parse_projection(tokens: &mut Lexer) -> Result<Vec<Projection>, Error> {
+    let mut projection = vec![Projection::parse(tokens)?];
+
+    while tokens.peek().map(|token| token.kind) == Some(TokenKind::Comma) {
+        tokens.next();
+        projection.push(Projection::parse(tokens)?);
+    }
+
+    Ok(projection)
+}
+
+/// The heap in `Query::order_and_limit` is used when there are
+/// at least this many times more `Row`s than the `limit`.
+const TOP_N_HEAP_RATIO: usize = 4;
+
+/// `OrderBy` represents the `order by <column> [collate nocase] [asc|desc]` clause.
+#[derive(Debug, PartialEq)]
+pub struct OrderBy {
+    pub column: Column,
+    pub nocase: bool,
+    pub descending: bool,
+}
+
+impl OrderBy {
+    /// Parses what comes after the `order` keyword.
+    fn parse(tokens: &mut Lexer) -> Result<Self, Error> {
+        expect_keyword(tokens, "by")?;
+
+        let column = parse_column(tokens, "order by")?;
+
+        let nocase = if tokens.peek().map(|token| token.text) == Some("collate") {
+            tokens.next();
+            expect_keyword(tokens, "nocase")?;
+            true
+        } else {
+            false
+        };
+
+        let descending = match tokens.peek().map(|token| token.text) {
+            Some("desc") => {
+                tokens.next();
+                true
+            }
+            Some("asc") => {
+                tokens.next();
+                false
+            }
+            _ => false,
+        };
+
+        Ok(OrderBy {
+            column,
+            nocase,
+            descending,
+        })
+    }
+
+    /// Compares two `Row`s by the chosen column.
+    ///
+    /// Text is compared byte-wise, so the order doesn't depend on
+    /// the locale. With `nocase`, ASCII letters are lowercased first.
+    pub fn compare(&self, a: &Row, b: &Row) -> Ordering {
+        let ordering = match self.column {
+            Column::RowId => a.rowid().cmp(&b.rowid()),
+            Column::Id => a.id().cmp(&b.id()),
+            Column::Username => compare_text(a.username(), b.username(), self.nocase),
+            Column::Email => compare_text(a.email(), b.email(), self.nocase),
+        };
+
+        if self.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+
+    /// Returns the first `n` `rows` in this order, the same as a stable
+    /// sort followed by a truncate, but without sorting all of them.
+    fn top_n<'a>(&self, rows: &[&'a Row], n: usize) -> Vec<&'a Row> {
+        let mut heap = BinaryHeap::with_capacity(n + 1);
+
+        for (index, row) in rows.iter().enumerate() {
+            heap.push(HeapEntry {
+                row,
+                index,
+                order_by: self,
+            });
+
+            if heap.len() > n {
+                heap.pop();
+            }
+        }
+
+        heap.into_sorted_vec()
+            .into_iter()
+            .map(|entry| entry.row)
+            .collect()
+    }
+}
+
+/// A `Row` inside the heap of `OrderBy::top_n`. The greatest entry is the
+/// last `Row` in the order, so it's the one popped when the heap is full.
+/// Ties are broken by the original `index`, keeping the sort stable.
+struct HeapEntry<'a, 'b> {
+    row: &'a Row,
+    index: usize,
+    order_by: &'b OrderBy,
+}
+
+impl Ord for HeapEntry<'_, '_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.order_by
+            .compare(self.row, other.row)
+            .then(self.index.cmp(&other.index))
+    }
+}
+
+impl PartialOrd for HeapEntry<'_, '_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for HeapEntry<'_, '_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for HeapEntry<'_, '_> {}
+
+fn compare_text(a: &str, b: &str, nocase: bool) -> Ordering {
+    if nocase {
+        a.bytes()
+            .map(|byte| byte.to_ascii_lowercase())
+            .cmp(b.bytes().map(|byte| byte.to_ascii_lowercase()))
+    } else {
+        a.as_bytes().cmp(b.as_bytes())
+    }
+}
+
+fn is_clause_keyword(token: &Token) -> bool {
+    ["where", "group", "order", "limit", "offset"].contains(&token.text)
+}
+
+/// Consumes the next token as a `Column`, `clause` is only used on the error message.
+fn parse_column(tokens: &mut Lexer, clause: &str) -> Result<Column, Error> {
+    let position = tokens.next_position();
+
+    match tokens.next() {
+        Some(token) => parse_column_token(token),
+        None => Err(at_position(
+            Error::SyntaxError(format!(
+                "Syntax error. Expected a column after '{}'",
+                clause
+            )),
+            position,
+        )),
+    }
+}
+
+/// Consumes the next token as the argument of a `function`, like
+/// `length`, which only accepts `username` and `email`.
+fn parse_text_column(tokens: &mut Lexer, function: &str) -> Result<Column, Error> {
+    let position = tokens.next_position();
+    let column = parse_column(tokens, function)?;
+
+    match column {
+        Column::Username | Column::Email => Ok(column),
+        Column::RowId | Column::Id => Err(at_position(
+            Error::SyntaxError(format!(
+                "Syntax error. '{}' requires a text column, not '{}'",
+                function, column
+            )),
+            position,
+        )),
+    }
+}
+
+fn parse_column_token(token: Token) -> Result<Column, Error> {
+    if token.kind != TokenKind::Word {
+        return Err(build_unexpected_token_error(token));
+    }
+
+    token
+        .text
+        .parse::<Column>()
+        .map_err(|error| at_position(error, token.position))
+}
+
+/// Consumes the next token, returning a `SyntaxError` if it isn't `keyword`.
+fn expect_keyword(tokens: &mut Lexer, keyword: &str) -> Result<(), Error> {
+    let position = tokens.next_position();
+
+    match tokens.next() {
+        Some(token) if token.text == keyword => Ok(()),
+        Some(token) => Err(build_unexpected_token_error(token)),
+        None => Err(at_position(
+            Error::SyntaxError(format!("Syntax error. Expected '{}'", keyword)),
+            position,
+        )),
+    }
+}
+
+fn build_unexpected_token_error(token: Token) -> Error {
+    at_position(
+        Error::SyntaxError(format!(
+            "Syntax error. Unexpected '{}' in select",
+            token.text
+        )),
+        token.position,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use crate::query::{OrderBy, Query};
+    use crate::row::{Column, Row};
+
+    fn build_rows() -> Vec<Row> {
+        // a simple linear congruential sequence, so ids repeat and are out of order
+        (0..200)
+            .map(|i| {
+                let id = (i * 37 + 11) % 53;
+                let username = format!("user{}", i % 7);
+
+                Row::new(id, &username, "user@mailbox.com").unwrap()
+            })
+            .collect()
+    }
+
+    fn sort_and_truncate<'a>(order_by: &OrderBy, rows: &[&'a Row], limit: usize) -> Vec<&'a Row> {
+        let mut sorted = rows.to_vec();
+        sorted.sort_by(|a, b| order_by.compare(a, b));
+        sorted.truncate(limit);
+        sorted
+    }
+
+    #[test]
+    fn order_and_limit_heap_matches_full_sort() {
+        let rows = build_rows();
+        let rows: Vec<&Row> = rows.iter().collect();
+
+        for &(column, descending) in &[
+            (Column::Id, true),
+            (Column::Id, false),
+            (Column::Username, true),
+        ] {
+            for &(limit, offset) in &[(0, 0), (1, 0), (5, 0), (20, 0), (2, 3)] {
+                let query = Query {
+                    order_by: Some(OrderBy {
+                        column,
+                        nocase: false,
+                        descending,
+                    }),
+                    limit: Some(limit),
+                    offset: Some(offset),
+                    ..Query::default()
+                };
+
+                let top_n = query.order_and_limit(rows.clone());
+                let expected =
+                    sort_and_truncate(query.order_by.as_ref().unwrap(), &rows, offset + limit);
+
+                let top_n: Vec<(usize, &str)> =
+                    top_n.iter().map(|r| (r.id(), r.username())).collect();
+                let expected: Vec<(usize, &str)> = expected
+                    .iter()
+                    .skip(offset)
+                    .map(|r| (r.id(), r.username()))
+                    .collect();
+
+                assert_eq!(
+                    top_n, expected,
+                    "{:?} desc {} limit {} offset {}",
+                    column, descending, limit, offset
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,49 @@
+/// The seed used by a `Random` until `.seed` sets another one.
+pub const DEFAULT_SEED: u64 = 0x5C00_117E;
+
+/// A small pseudo random number generator (SplitMix64), good enough for
+/// sampling `Row`s and, with the same seed, it always returns the same numbers.
+///
+/// Here's an example:
+/// ```
+/// use scoolite::random::Random;
+///
+/// let mut a = Random::new(42);
+/// let mut b = Random::new(42);
+///
+/// assert_eq!(a.next_u64(), b.next_u64());
+/// assert!(a.below(10) < 10);
+/// ```
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    /// Creates a generator starting from `seed`.
+    pub fn new(seed: u64) -> Self {
+        Random { state: seed }
+    }
+
+    /// Returns the next number of the sequence.
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number from `0` up to, but not including, `bound`,
+    /// which can't be `0`.
+    pub fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+}
+
+impl Default for Random {
+    fn default() -> Self {
+        Random::new(DEFAULT_SEED)
+    }
+}
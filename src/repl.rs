@@ -0,0 +1,605 @@
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+use crate::command::{
+    build_command, build_usage_error, execute_command, CommandKind, CommandRegistry,
+};
+use crate::error::Error;
+use crate::format::Format;
+use crate::table::Table;
+
+/// `Repl` holds the state of an interactive session: the `Table`
+/// and everything about where the output of the commands goes.
+pub struct Repl {
+    table: Table,
+    format: Format,
+    registry: CommandRegistry,
+    output: Option<File>,
+    tee: Option<File>,
+    watched: Option<String>,
+    profile: Profile,
+    variables: BTreeMap<String, String>,
+}
+
+impl Repl {
+    /// Creates a new session with an empty `Table`, writing to stdout.
+    pub fn new() -> Self {
+        Repl::with_table(Table::new())
+    }
+
+    /// Creates a new session with a `Table::new_readonly`, where no
+    /// command can change the `Table` nor open output files.
+    pub fn new_readonly() -> Self {
+        Repl::with_table(Table::new_readonly())
+    }
+
+    fn with_table(table: Table) -> Self {
+        Repl {
+            table,
+            format: Format::new(),
+            registry: CommandRegistry::new(),
+            output: None,
+            tee: None,
+            watched: None,
+            profile: Profile::default(),
+            variables: BTreeMap::new(),
+        }
+    }
+
+    /// Replaces the custom commands of the session by the ones of the `registry`.
+    pub fn set_registry(&mut self, registry: CommandRegistry) {
+        self.registry = registry;
+    }
+
+    /// Runs a command like `run_command` does, but first it handles the
+    /// meta commands that only make sense for a session, like `.output`.
+    ///
+    /// When the output is redirected to a file, the command's output is
+    /// written there and an empty `String` is returned instead.
+    ///
+    /// If there's a watched command, a blank `input` runs it again.
+    ///
+    /// The `$name` references to variables set with `.set` are replaced
+    /// by their values before the command is parsed.
+    ///
+    /// With `.tee`, the command's output is also appended to the tee file,
+    /// wherever the output goes. This is the same for the output of the
+    /// session's meta commands, like `.show`.
+    pub fn run_command(&mut self, input: String) -> Result<String, Error> {
+        let output = match ReplCommand::from_str(input.trim())? {
+            Some(repl_command) => self.execute(repl_command)?,
+            None => self.run_table_command(input)?,
+        };
+
+        self.write_output(output)
+    }
+
+    /// Runs a command that isn't a `ReplCommand` on the `Table`,
+    /// recording how long it took in the `Profile`.
+    fn run_table_command(&mut self, input: String) -> Result<String, Error> {
+        let input = match &self.watched {
+            Some(watched) if input.trim().is_empty() => watched.clone(),
+            _ => input,
+        };
+
+        let input = self.substitute_variables(&input)?;
+        let command = build_command(&input, &self.registry)?;
+
+        let start = Instant::now();
+        let result = execute_command(command.as_ref(), &mut self.table, &mut self.format);
+        self.profile.record(command.command_kind(), start.elapsed());
+
+        result
+    }
+
+    /// Appends the `output` to the tee file, if any, and writes it to
+    /// the output file, returning an empty `String`, or returns it.
+    fn write_output(&mut self, output: String) -> Result<String, Error> {
+        if let Some(file) = &mut self.tee {
+            file.write_all(output.as_bytes())
+                .map_err(|error| Error::Io(format!("Failed to write tee: {}", error)))?;
+        }
+
+        match &mut self.output {
+            Some(file) => {
+                file.write_all(output.as_bytes())
+                    .map_err(|error| Error::Io(format!("Failed to write output: {}", error)))?;
+
+                Ok("".to_string())
+            }
+            None => Ok(output),
+        }
+    }
+
+    /// Executes an different logic for each variant of the `enum`.
+    fn execute(&mut self, repl_command: ReplCommand) -> Result<String, Error> {
+        match repl_command {
+            ReplCommand::Output(target) if target == "stdout" => {
+                self.output = None;
+            }
+            ReplCommand::Tee(target) if target == "off" => {
+                self.tee = None;
+            }
+            ReplCommand::Output(_) | ReplCommand::Tee(_) if self.table.is_readonly() => {
+                return Err(Error::ReadOnly(
+                    "Read-only mode. Output files can't be opened".to_string(),
+                ))
+            }
+            ReplCommand::Output(path) => {
+                let file = File::create(&path)
+                    .map_err(|error| Error::Io(format!("Failed to open '{}': {}", path, error)))?;
+
+                self.output = Some(file);
+            }
+            ReplCommand::Tee(path) => {
+                let file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&path)
+                    .map_err(|error| Error::Io(format!("Failed to open '{}': {}", path, error)))?;
+
+                self.tee = Some(file);
+            }
+            ReplCommand::Watch(command) => {
+                self.watched = Some(command);
+            }
+            ReplCommand::Unwatch => {
+                self.watched = None;
+            }
+            ReplCommand::Profile(enabled) => {
+                self.profile.enabled = enabled;
+            }
+            ReplCommand::ProfileReset => {
+                self.profile.timings.clear();
+            }
+            ReplCommand::ProfileReport => return Ok(self.profile.report()),
+            ReplCommand::Set(name, value) => {
+                self.variables.insert(name, value);
+            }
+            ReplCommand::Validate => return Ok("OK\n".to_string()),
+            ReplCommand::Show => {
+                return Ok(self
+                    .variables
+                    .iter()
+                    .map(|(name, value)| format!("{} = {}\n", name, value))
+                    .collect())
+            }
+        }
+
+        Ok("".to_string())
+    }
+
+    /// Replaces every `$name` of the `input` by the value of the variable,
+    /// returning a `SyntaxError` if it was never set. A `$` without a name
+    /// after it is kept as it is.
+    fn substitute_variables(&self, input: &str) -> Result<String, Error> {
+        let mut output = String::new();
+        let mut rest = input;
+
+        while let Some(start) = rest.find('$') {
+            output.push_str(&rest[..start]);
+            rest = &rest[start + 1..];
+
+            let end = rest
+                .find(|character: char| !character.is_alphanumeric() && character != '_')
+                .unwrap_or(rest.len());
+            let name = &rest[..end];
+
+            if name.is_empty() {
+                output.push('$');
+            } else {
+                let value = self.variables.get(name).ok_or_else(|| {
+                    Error::SyntaxError(format!("Syntax error. Unknown variable '${}'", name))
+                })?;
+
+                output.push_str(value);
+            }
+
+            rest = &rest[end..];
+        }
+
+        output.push_str(rest);
+
+        Ok(output)
+    }
+}
+
+impl Default for Repl {
+    fn default() -> Self {
+        Repl::new()
+    }
+}
+
+/// `ReplCommand` is the `enum` that contains the meta commands that change
+/// the state of the `Repl` instead of the `Table`.
+/// An example is `.output results.txt`, that redirects all of the following
+/// output to the `results.txt` file until `.output stdout`.
+#[derive(Debug, PartialEq)]
+enum ReplCommand {
+    Output(String),
+    /// Appends the output to a file too, until `.tee off`.
+    Tee(String),
+    /// Stores a command to be run again on every blank input.
+    Watch(String),
+    Unwatch,
+    /// Turns the `Profile` of the commands on or off.
+    Profile(bool),
+    ProfileReset,
+    ProfileReport,
+    /// Sets a variable, referenced as `$name` in the following commands.
+    Set(String, String),
+    /// Lists the variables and their values.
+    Show,
+    /// `.validate` of a `ReplCommand`, it was parsed without running it.
+    Validate,
+}
+
+/// How each `ReplCommand` is called, by its name.
+const REPL_COMMAND_USAGES: &[(&str, &str)] = &[
+    (".output", ".output <path>|stdout"),
+    (".tee", ".tee <path>|off"),
+    (".watch", ".watch <command>"),
+    (".unwatch", ".unwatch"),
+    (".profile", ".profile on|off|reset|report"),
+    (".set", ".set <name> <value>"),
+    (".show", ".show"),
+];
+
+impl ReplCommand {
+    /// Tries to parse an `&str` `input` into a `ReplCommand`, if it isn't one
+    /// it returns `None`, so that it can be handled as a regular command.
+    /// A `ReplCommand` with the wrong arguments returns its usage as an `Error`.
+    fn from_str(input: &str) -> Result<Option<Self>, Error> {
+        let arguments: Vec<&str> = input.split_whitespace().collect();
+
+        let repl_command = match arguments.as_slice() {
+            [".output", target] => ReplCommand::Output(target.to_string()),
+            [".tee", target] => ReplCommand::Tee(target.to_string()),
+            [".watch", _, ..] => ReplCommand::Watch(input[".watch".len()..].trim().to_string()),
+            [".unwatch"] => ReplCommand::Unwatch,
+            [".profile", "on"] => ReplCommand::Profile(true),
+            [".profile", "off"] => ReplCommand::Profile(false),
+            [".profile", "reset"] => ReplCommand::ProfileReset,
+            [".profile", "report"] => ReplCommand::ProfileReport,
+            [".set", name, value] => ReplCommand::Set(name.to_string(), value.to_string()),
+            [".show"] => ReplCommand::Show,
+            [".validate", _, ..] => match ReplCommand::from_str(input[".validate".len()..].trim())?
+            {
+                Some(_) => ReplCommand::Validate,
+                None => return Ok(None),
+            },
+            [name, ..] => {
+                return match REPL_COMMAND_USAGES.iter().find(|(known, _)| known == name) {
+                    Some((_, usage)) => Err(build_usage_error(usage)),
+                    None => Ok(None),
+                }
+            }
+            [] => return Ok(None),
+        };
+
+        Ok(Some(repl_command))
+    }
+}
+
+/// How many times each kind of command ran and for how long,
+/// it only records while it's `enabled`.
+#[derive(Default)]
+struct Profile {
+    enabled: bool,
+    timings: BTreeMap<String, (usize, Duration)>,
+}
+
+impl Profile {
+    /// Adds a call of a command of `kind` that took `elapsed`,
+    /// every meta command is recorded together as `meta`,
+    /// and empty statements, a lone `;`, aren't recorded at all.
+    fn record(&mut self, kind: CommandKind, elapsed: Duration) {
+        if !self.enabled || kind == CommandKind::Empty {
+            return;
+        }
+
+        let name = match kind {
+            CommandKind::Meta(_) => "meta".to_string(),
+            kind => kind.to_string(),
+        };
+
+        let (calls, total) = self.timings.entry(name).or_default();
+        *calls += 1;
+        *total += elapsed;
+    }
+
+    /// One line for each kind of command, like `insert: 2 calls, 0.015 ms`.
+    fn report(&self) -> String {
+        self.timings
+            .iter()
+            .map(|(kind, (calls, total))| {
+                format!(
+                    "{}: {} calls, {:.3} ms\n",
+                    kind,
+                    calls,
+                    total.as_secs_f64() * 1000.0
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::fs;
+
+    use crate::command::CommandRegistry;
+    use crate::error::Error;
+    use crate::repl::Repl;
+
+    #[test]
+    fn run_command_output_to_file() {
+        let path = env::temp_dir().join("scoolite_run_command_output_to_file.txt");
+        let path = path.to_str().unwrap();
+
+        let mut repl = Repl::new();
+
+        repl.run_command("insert 1 john john@mailbox.com".to_string())
+            .unwrap();
+        repl.run_command(format!(".output {}", path)).unwrap();
+
+        let output = repl.run_command("select".to_string()).unwrap();
+
+        assert_eq!(output, "");
+
+        repl.run_command(".output stdout".to_string()).unwrap();
+
+        let output = repl.run_command("select".to_string()).unwrap();
+
+        assert_eq!(output, "(1, john, john@mailbox.com)\nExecuted.\n");
+        assert_eq!(
+            fs::read_to_string(path).unwrap(),
+            "(1, john, john@mailbox.com)\nExecuted.\n"
+        );
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn run_command_output_to_invalid_path() {
+        let mut repl = Repl::new();
+
+        let error = repl
+            .run_command(".output /unexistent/directory/file.txt".to_string())
+            .unwrap_err();
+
+        match error {
+            Error::Io(message) => {
+                assert!(message.starts_with("Failed to open '/unexistent/directory/file.txt'"))
+            }
+            _ => panic!("expected an Io error, got {:?}", error),
+        }
+    }
+
+    #[test]
+    fn run_command_watch_reruns_on_blank_input() {
+        let mut repl = Repl::new();
+
+        repl.run_command("insert 1 john john@mailbox.com".to_string())
+            .unwrap();
+        repl.run_command(".watch select count".to_string()).unwrap();
+
+        let output = repl.run_command("\n".to_string()).unwrap();
+
+        assert_eq!(output, "(1)\nExecuted.\n");
+
+        repl.run_command("insert 2 jane jane@mailbox.com".to_string())
+            .unwrap();
+
+        let output = repl.run_command("".to_string()).unwrap();
+
+        assert_eq!(output, "(2)\nExecuted.\n");
+
+        repl.run_command(".unwatch".to_string()).unwrap();
+
+        let error = repl.run_command("\n".to_string()).unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::UnrecognizedStatement("Unrecognized keyword at start of ''".to_string())
+        );
+    }
+
+    #[test]
+    fn run_command_profile_counts_calls_per_kind() {
+        let mut repl = Repl::new();
+
+        repl.run_command("insert 1 john john@mailbox.com".to_string())
+            .unwrap();
+        repl.run_command(".profile on".to_string()).unwrap();
+
+        for id in 2..=3 {
+            repl.run_command(format!("insert {} user{} user{}@mail.com", id, id, id))
+                .unwrap();
+        }
+
+        repl.run_command("select".to_string()).unwrap();
+        repl.run_command(";".to_string()).unwrap();
+        repl.run_command(".cache on".to_string()).unwrap();
+        repl.run_command(".mode list".to_string()).unwrap();
+        repl.run_command(".profile off".to_string()).unwrap();
+        repl.run_command("select".to_string()).unwrap();
+
+        let report = repl.run_command(".profile report".to_string()).unwrap();
+        let calls: Vec<&str> = report
+            .lines()
+            .map(|line| line.split(" calls").next().unwrap())
+            .collect();
+
+        assert_eq!(calls, vec!["insert: 2", "meta: 2", "select: 1"]);
+
+        repl.run_command(".profile reset".to_string()).unwrap();
+
+        let report = repl.run_command(".profile report".to_string()).unwrap();
+
+        assert_eq!(report, "");
+    }
+
+    #[test]
+    fn run_command_tee_to_file() {
+        let path = env::temp_dir().join("scoolite_run_command_tee_to_file.txt");
+        let path = path.to_str().unwrap();
+        let _ = fs::remove_file(path);
+
+        let mut repl = Repl::new();
+
+        repl.run_command("insert 1 john john@mailbox.com".to_string())
+            .unwrap();
+        repl.run_command(format!(".tee {}", path)).unwrap();
+
+        let output = repl.run_command("select".to_string()).unwrap();
+
+        assert_eq!(output, "(1, john, john@mailbox.com)\nExecuted.\n");
+
+        repl.run_command(".tee off".to_string()).unwrap();
+        repl.run_command("select".to_string()).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(path).unwrap(),
+            "(1, john, john@mailbox.com)\nExecuted.\n"
+        );
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn run_command_show_goes_to_output_and_tee() {
+        let directory = env::temp_dir();
+        let output_path = directory.join("scoolite_run_command_show_output.txt");
+        let output_path = output_path.to_str().unwrap();
+        let tee_path = directory.join("scoolite_run_command_show_tee.txt");
+        let tee_path = tee_path.to_str().unwrap();
+        let _ = fs::remove_file(tee_path);
+
+        let mut repl = Repl::new();
+
+        repl.run_command(".set user john".to_string()).unwrap();
+        repl.run_command(format!(".tee {}", tee_path)).unwrap();
+
+        let output = repl.run_command(".show".to_string()).unwrap();
+
+        assert_eq!(output, "user = john\n");
+
+        repl.run_command(format!(".output {}", output_path))
+            .unwrap();
+
+        let output = repl.run_command(".show".to_string()).unwrap();
+
+        assert_eq!(output, "");
+
+        repl.run_command(".output stdout".to_string()).unwrap();
+        repl.run_command(".tee off".to_string()).unwrap();
+
+        assert_eq!(fs::read_to_string(output_path).unwrap(), "user = john\n");
+        assert_eq!(
+            fs::read_to_string(tee_path).unwrap(),
+            "user = john\nuser = john\n"
+        );
+
+        fs::remove_file(output_path).unwrap();
+        fs::remove_file(tee_path).unwrap();
+    }
+
+    #[test]
+    fn run_command_custom_commands_and_validate() {
+        let mut registry = CommandRegistry::new();
+        registry.register_command("ping", |_input, _table| Ok("pong".to_string()));
+
+        let mut repl = Repl::new();
+        repl.set_registry(registry);
+
+        assert_eq!(repl.run_command("ping".to_string()), Ok("pong".to_string()));
+
+        for input in &[
+            ".validate ping",
+            ".validate .output x",
+            ".validate .profile on",
+        ] {
+            let output = repl.run_command(input.to_string());
+
+            assert_eq!(output, Ok("OK\n".to_string()), "input {}", input);
+        }
+
+        let error = repl
+            .run_command(".validate .set x".to_string())
+            .unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::SyntaxError("Syntax error. Usage: .set <name> <value>".to_string())
+        );
+    }
+
+    #[test]
+    fn run_command_variables() {
+        let mut repl = Repl::new();
+
+        repl.run_command(".set user john".to_string()).unwrap();
+        repl.run_command(".set id 7".to_string()).unwrap();
+        repl.run_command("insert $id $user $user@mailbox.com".to_string())
+            .unwrap();
+
+        let output = repl.run_command("select".to_string()).unwrap();
+
+        assert_eq!(output, "(7, john, john@mailbox.com)\nExecuted.\n");
+
+        let output = repl.run_command(".show".to_string()).unwrap();
+
+        assert_eq!(output, "id = 7\nuser = john\n");
+
+        let error = repl
+            .run_command("insert 8 $name a@b.com".to_string())
+            .unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::SyntaxError("Syntax error. Unknown variable '$name'".to_string())
+        );
+
+        let error = repl.run_command(".set x".to_string()).unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::SyntaxError("Syntax error. Usage: .set <name> <value>".to_string())
+        );
+    }
+
+    #[test]
+    fn run_command_readonly_rejects_output_files() {
+        let path = env::temp_dir().join("scoolite_run_command_readonly_rejects_output_files.txt");
+        let path = path.to_str().unwrap();
+
+        let mut repl = Repl::new_readonly();
+
+        for command in &[".output", ".tee"] {
+            let error = repl
+                .run_command(format!("{} {}", command, path))
+                .unwrap_err();
+
+            assert_eq!(
+                error,
+                Error::ReadOnly("Read-only mode. Output files can't be opened".to_string())
+            );
+        }
+
+        assert!(fs::metadata(path).is_err());
+
+        repl.run_command(".output stdout".to_string()).unwrap();
+        repl.run_command(".tee off".to_string()).unwrap();
+
+        let error = repl.run_command(".readonly off".to_string()).unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::ReadOnly("Read-only mode. The table was created read-only".to_string())
+        );
+    }
+}
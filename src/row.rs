@@ -1,43 +1,240 @@
 use crate::error::Error;
+use crate::intern::Interner;
+use crate::lexer::{at_position, parse_number, Lexer, Token, TokenKind};
 use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// The maximum amount of characters of a `username`.
+///
+/// Inserts with a longer `username`, which used to be accepted,
+/// are rejected with a `SyntaxError`, unless `.truncate` is on.
+pub const USERNAME_MAX_LENGTH: usize = 32;
+
+/// The maximum amount of characters of an `email`.
+///
+/// Inserts with a longer `email`, which used to be accepted,
+/// are rejected with a `SyntaxError`, unless `.truncate` is on.
+pub const EMAIL_MAX_LENGTH: usize = 255;
 
 /// This struct contains the first table
 /// available for storage. It will be
 /// defined by the user eventually.
+///
+/// Besides the user's `id`, each `Row` has a hidden `rowid`, assigned
+/// by the `Table` when it gets inserted, that keeps the insertion order.
 pub struct Row {
+    rowid: usize,
     id: usize,
-    username: String,
-    email: String,
+    username: Arc<str>,
+    email: Arc<str>,
 }
 
 impl Row {
-    /// Receives an input like `1 john john@mailbox.com` and
-    /// it builds a `Row` with these fields. If any errors happen
-    /// on the parse step, it will return an `Error`.
-    pub fn from_str(input: &str) -> Result<Self, Error> {
-        let data: Vec<&str> = input.split(' ').skip(1).collect();
+    /// Builds a `Row` from its values, applying the same validation
+    /// as parsing it from an `insert` input, so that library users
+    /// don't need to format a `String` first. That includes the
+    /// `USERNAME_MAX_LENGTH` and `EMAIL_MAX_LENGTH` limits.
+    pub fn new(id: usize, username: &str, email: &str) -> Result<Self, Error> {
+        Row::build(id, username, email, false)
+    }
 
+    /// Builds a `Row` like `new`, but with `truncate` the text values
+    /// longer than their maximum are cut, on a `char` boundary.
+    fn build(id: usize, username: &str, email: &str, truncate: bool) -> Result<Self, Error> {
         Ok(Row {
-            id: data
-                .get(0)
-                .and_then(|id_string| id_string.parse::<usize>().ok())
-                .ok_or(Error::SyntaxError(
-                    "Syntax error. Failed to parse 'id' of input".to_string(),
-                ))?,
-            username: data
-                .get(1)
-                .map(|a| a.to_string())
-                .ok_or(Error::SyntaxError(
-                    "Syntax error. Failed to parse 'username' of input".to_string(),
-                ))?,
-            email: data
-                .get(2)
-                .map(|a| a.to_string())
-                .ok_or(Error::SyntaxError(
-                    "Syntax error. Failed to parse 'email' of input".to_string(),
-                ))?,
+            rowid: 0,
+            id,
+            username: parse_text("username", username, USERNAME_MAX_LENGTH, truncate)?.into(),
+            email: parse_text("email", email, EMAIL_MAX_LENGTH, truncate)?.into(),
         })
     }
+
+    /// Parses the values of a `Row`, like `1 john john@mailbox.com`,
+    /// those must be the last `Token`s of the input.
+    pub fn parse(tokens: &mut Lexer) -> Result<Self, Error> {
+        Row::parse_with_options(tokens, ParseOptions::default())
+    }
+
+    /// Does the same as `parse`, but following the `options`.
+    pub fn parse_with_options(tokens: &mut Lexer, options: ParseOptions) -> Result<Self, Error> {
+        let row = Row::parse_fields(tokens, None, options)?;
+
+        tokens.expect_end()?;
+
+        Ok(row)
+    }
+
+    /// Parses the values of a `Row` written as a tuple, like
+    /// `(1, john, john@mailbox.com)`. All of its values must be
+    /// there, so the `default_email` of the `options` isn't used.
+    pub fn parse_tuple(tokens: &mut Lexer, options: ParseOptions) -> Result<Self, Error> {
+        tokens.expect(TokenKind::OpenParen)?;
+
+        let row = Row::parse_fields(tokens, Some(TokenKind::Comma), options)?;
+
+        tokens.expect(TokenKind::CloseParen)?;
+
+        Ok(row)
+    }
+
+    /// Parses the `id`, `username` and `email`, with a `separator`
+    /// between them if there's one. A missing `email` is only
+    /// fine if there's a `default_email`.
+    fn parse_fields(
+        tokens: &mut Lexer,
+        separator: Option<TokenKind>,
+        options: ParseOptions,
+    ) -> Result<Self, Error> {
+        let expect_separator = |tokens: &mut Lexer| match separator {
+            Some(separator) => tokens.expect(separator),
+            None => Ok(()),
+        };
+
+        let id = next_field(tokens, "id")?;
+        let id = parse_number(id.text)
+            .ok_or_else(|| at_position(build_parse_error("id", id.text), id.position))?;
+
+        expect_separator(tokens)?;
+        let username = next_field(tokens, "username")?;
+
+        if let (None, Some(default_email)) = (tokens.peek(), options.default_email) {
+            return Row::build(id, username.text, default_email, options.truncate);
+        }
+
+        expect_separator(tokens)?;
+        let email = next_field(tokens, "email")?;
+
+        Row::build(id, username.text, email.text, options.truncate)
+    }
+
+    /// Returns the internal `rowid` of the `Row`, it's `0`
+    /// while the `Row` hasn't been inserted in a `Table`.
+    pub fn rowid(&self) -> usize {
+        self.rowid
+    }
+
+    pub(crate) fn set_rowid(&mut self, rowid: usize) {
+        self.rowid = rowid;
+    }
+
+    /// Returns the `id` of the `Row`.
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub(crate) fn set_id(&mut self, id: usize) {
+        self.id = id;
+    }
+
+    /// Returns the `username` of the `Row`.
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+
+    /// Returns the `email` of the `Row`.
+    pub fn email(&self) -> &str {
+        &self.email
+    }
+
+    /// Returns the text values, which may be shared with other `Row`s.
+    pub(crate) fn text_values(&self) -> [&Arc<str>; 2] {
+        [&self.username, &self.email]
+    }
+
+    /// Replaces the text values with their shared copies from the `interner`.
+    pub(crate) fn intern(&mut self, interner: &mut Interner) {
+        self.username = interner.intern(&self.username);
+        self.email = interner.intern(&self.email);
+    }
+
+    /// Returns the value of `column` formatted as a `String`,
+    /// the same way it is shown when the `Row` is displayed.
+    pub fn get(&self, column: Column) -> String {
+        match column {
+            Column::RowId => self.rowid.to_string(),
+            Column::Id => self.id.to_string(),
+            Column::Username => self.username.to_string(),
+            Column::Email => self.email.to_string(),
+        }
+    }
+
+    /// Returns the value of `column` as a number, if it's a numeric one.
+    pub fn get_number(&self, column: Column) -> Option<usize> {
+        match column {
+            Column::RowId => Some(self.rowid),
+            Column::Id => Some(self.id),
+            Column::Username | Column::Email => None,
+        }
+    }
+}
+
+impl FromStr for Row {
+    type Err = Error;
+
+    /// Receives an input like `insert 1 john john@mailbox.com` and
+    /// it builds a `Row` with these fields. If any errors happen
+    /// on the parse step, it will return an `Error`.
+    fn from_str(input: &str) -> Result<Self, Error> {
+        let mut tokens = Lexer::new(input);
+        tokens.next();
+
+        Row::parse(&mut tokens)
+    }
+}
+
+/// Settings of the `Table` that change how the values of an `insert` are parsed.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ParseOptions<'a> {
+    /// The `email` of inputs that end right after the `username`, like `1 john`.
+    pub default_email: Option<&'a str>,
+    /// Cuts text values longer than their maximum instead of returning an `Error`.
+    pub truncate: bool,
+}
+
+/// Consumes the next `Token` as the value of `column`, it must be a word.
+fn next_field<'a>(tokens: &mut Lexer<'a>, column: &str) -> Result<Token<'a>, Error> {
+    let position = tokens.next_position();
+
+    match tokens.next() {
+        Some(token) if token.kind == TokenKind::Word => Ok(token),
+        Some(token) => Err(at_position(build_parse_error(column, token.text), position)),
+        None => Err(at_position(build_parse_error(column, ""), position)),
+    }
+}
+
+/// Text columns can't be empty nor have more than `max_length` characters,
+/// unless they're `truncate`d to it.
+fn parse_text(
+    column: &str,
+    value: &str,
+    max_length: usize,
+    truncate: bool,
+) -> Result<String, Error> {
+    if value.is_empty() {
+        return Err(build_parse_error(column, value));
+    }
+
+    if truncate {
+        return Ok(value.chars().take(max_length).collect());
+    }
+
+    if value.chars().count() > max_length {
+        return Err(Error::SyntaxError(format!(
+            "Syntax error. '{}' is longer than {} characters",
+            column, max_length
+        )));
+    }
+
+    Ok(value.to_string())
+}
+
+/// Creates the `SyntaxError` for when the `value` of a `column` can't be parsed.
+fn build_parse_error(column: &str, value: &str) -> Error {
+    Error::SyntaxError(format!(
+        "Syntax error. Failed to parse '{}' from '{}'",
+        column, value
+    ))
 }
 
 impl fmt::Display for Row {
@@ -50,3 +247,46 @@ impl fmt::Display for Row {
         write!(f, "({}, {}, {})", self.id, self.username, self.email)
     }
 }
+
+/// Each variant represents one of the fields of a `Row`, so that
+/// clauses like `order by` can refer to them by name.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Column {
+    RowId,
+    Id,
+    Username,
+    Email,
+}
+
+impl fmt::Display for Column {
+    /// Writes the name of the column, the same one used to parse it.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Column::RowId => "rowid",
+            Column::Id => "id",
+            Column::Username => "username",
+            Column::Email => "email",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Column {
+    type Err = Error;
+
+    /// Parses a column name like `username` into a `Column`, if the
+    /// name is unknown it returns a `SyntaxError`.
+    fn from_str(input: &str) -> Result<Self, Error> {
+        match input {
+            "rowid" => Ok(Column::RowId),
+            "id" => Ok(Column::Id),
+            "username" => Ok(Column::Username),
+            "email" => Ok(Column::Email),
+            _ => Err(Error::SyntaxError(format!(
+                "Syntax error. Unknown column '{}'",
+                input
+            ))),
+        }
+    }
+}
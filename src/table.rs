@@ -1,23 +1,531 @@
-use crate::row::Row;
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+use std::mem;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+use crate::error::Error;
+use crate::format::Format;
+use crate::intern::Interner;
+use crate::random::Random;
+use crate::row::{ParseOptions, Row};
+
+/// A business rule of an embedder, it gets every `Row` that is about
+/// to be inserted or updated and rejects it by returning an `Error`,
+/// usually an `Error::Custom`.
+pub type RowValidator = dyn Fn(&Row) -> Result<(), Error> + Send + Sync;
+
+/// The name of the only `Table`, used by statements like `insert into users`.
+pub const TABLE_NAME: &str = "users";
 
 /// In memory storage of `Row`s.
 pub struct Table {
     rows: Vec<Row>,
+    last_rowid: usize,
+    cache: Mutex<SelectCache>,
+    readonly: bool,
+    readonly_locked: bool,
+    random: Mutex<Random>,
+    allowed_domains: Vec<String>,
+    undo: Option<Undo>,
+    description: Option<String>,
+    interner: Option<Interner>,
+    validators: Vec<Box<RowValidator>>,
+    default_email: Option<String>,
+    truncate: bool,
+    timeout: Option<Duration>,
+}
+
+thread_local! {
+    /// When the command running on this thread goes over its `timeout`, it's
+    /// kept per thread since the readers of a `Database` run commands at once.
+    static DEADLINE: Cell<Option<Instant>> = const { Cell::new(None) };
+}
+
+/// How to reverse the last mutating command, see `Table::undo`.
+pub enum Undo {
+    /// Removes the `Row`s after the first `count` ones, since inserts only append them.
+    Truncate(usize),
+    /// Gives the `Row` with the `rowid` its previous `id`, `from`.
+    Rekey { rowid: usize, from: usize },
+    /// Puts back the `Row`s removed by a delete, in their `rowid` order.
+    Restore(Vec<Row>),
+}
+
+/// Results of previous `select`s keyed by their normalized input and the
+/// `Format` they were written with. Every mutation of the `Table` clears it,
+/// it's behind a `Mutex` so that a `select` only needs shared access.
+#[derive(Default)]
+struct SelectCache {
+    enabled: bool,
+    results: HashMap<(String, Format), String>,
+    hits: usize,
 }
 
 impl Table {
     /// Creates a new table.
     pub fn new() -> Self {
-        Table { rows: vec![] }
+        Table {
+            rows: vec![],
+            last_rowid: 0,
+            cache: Mutex::default(),
+            readonly: false,
+            readonly_locked: false,
+            random: Mutex::default(),
+            allowed_domains: vec![],
+            undo: None,
+            description: None,
+            interner: None,
+            validators: vec![],
+            default_email: None,
+            truncate: false,
+            timeout: None,
+        }
     }
 
-    /// Adds a `Row` into the `rows` `Vec`.
+    /// Adds a `Row` into the `rows` `Vec`, assigning it
+    /// the next `rowid`, starting from `1`.
     pub fn add_row(&mut self, row: Row) {
-        self.rows.push(row);
+        self.add_rows(vec![row]);
     }
 
-    /// Returns a reference to all `Row`s inside the table.
+    /// Adds many `Row`s like `add_row`, a single `undo` removes all of them.
+    pub fn add_rows(&mut self, rows: Vec<Row>) {
+        if rows.is_empty() {
+            return;
+        }
+
+        self.set_undo(Undo::Truncate(self.rows.len()));
+
+        for mut row in rows {
+            if let Some(interner) = &mut self.interner {
+                row.intern(interner);
+            }
+
+            self.last_rowid += 1;
+            row.set_rowid(self.last_rowid);
+
+            self.rows.push(row);
+        }
+
+        self.clear_cache();
+    }
+
+    /// Returns a reference to all `Row`s inside the table, always in `rowid`
+    /// order, since `Row`s are only appended, and removed keeping the order.
     pub fn list_rows(&self) -> &Vec<Row> {
         &self.rows
     }
+
+    /// Returns the `Row`s with a `rowid` greater than `rowid`, found with
+    /// a binary search since the `Row`s are in `rowid` order.
+    pub fn rows_since(&self, rowid: usize) -> &[Row] {
+        let start = self.rows.partition_point(|row| row.rowid() <= rowid);
+
+        &self.rows[start..]
+    }
+
+    /// Returns how many `Row`s are inside the table.
+    pub fn count(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Returns the `Row` with the given `id`, if there's any.
+    pub fn find_row(&self, id: usize) -> Option<&Row> {
+        self.rows.iter().find(|row| row.id() == id)
+    }
+
+    /// Estimates how many bytes of memory the stored `Row`s use.
+    /// It isn't exact, but it grows with the amount of `Row`s
+    /// and the size of their text values, counting each
+    /// interned value only once, and the `Interner` pool too.
+    pub fn approx_memory_bytes(&self) -> usize {
+        let mut seen_values = HashSet::new();
+
+        let pool_values = self.interner.iter().flat_map(Interner::values);
+
+        let text_bytes: usize = self
+            .rows
+            .iter()
+            .flat_map(|row| row.text_values())
+            .chain(pool_values)
+            .filter(|value| seen_values.insert(Arc::as_ptr(value) as *const u8))
+            .map(|value| value.len())
+            .sum();
+
+        let pool_bytes = self.interner.as_ref().map_or(0, |interner| {
+            interner.capacity() * mem::size_of::<Arc<str>>()
+        });
+
+        self.rows.capacity() * mem::size_of::<Row>() + pool_bytes + text_bytes
+    }
+
+    /// Turns interning of the text values of new `Row`s on or off,
+    /// turning it off forgets the shared values, but `Row`s keep theirs.
+    pub fn set_interning(&mut self, enabled: bool) {
+        if !enabled {
+            self.interner = None;
+        } else if self.interner.is_none() {
+            self.interner = Some(Interner::new());
+        }
+    }
+
+    /// Returns the dedup ratio of the `Interner`, if interning is on.
+    pub fn intern_dedup_ratio(&self) -> Option<f64> {
+        self.interner
+            .as_ref()
+            .map(|interner| interner.dedup_ratio())
+    }
+
+    /// Turns the `select` cache on or off, turning it off also
+    /// drops every cached result.
+    pub fn set_cache_enabled(&mut self, enabled: bool) {
+        let cache = self.cache.get_mut().unwrap_or_else(PoisonError::into_inner);
+        cache.enabled = enabled;
+        cache.results.clear();
+    }
+
+    /// Returns the cached result of the `select` `input` written with the
+    /// `format`, counting it as a cache hit. It's always `None` if the cache is off.
+    pub fn cached_select(&self, input: &str, format: &Format) -> Option<String> {
+        let mut cache = self.cache();
+        let key = (input.to_string(), format.clone());
+        let result = cache.results.get(&key).cloned();
+
+        if result.is_some() {
+            cache.hits += 1;
+        }
+
+        result
+    }
+
+    /// Stores the `result` of the `select` `input` written with the `format`,
+    /// if the cache is on.
+    pub fn cache_select(&self, input: String, format: &Format, result: String) {
+        let mut cache = self.cache();
+
+        if cache.enabled {
+            cache.results.insert((input, format.clone()), result);
+        }
+    }
+
+    /// Returns how many `select`s were answered by the cache.
+    pub fn cache_hits(&self) -> usize {
+        self.cache().hits
+    }
+
+    /// Locks the cache, a `select` that panicked can only leave
+    /// it without its result, so a poisoned lock is recovered.
+    fn cache(&self) -> MutexGuard<'_, SelectCache> {
+        self.cache.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Drops every cached result, after the `Row`s change.
+    fn clear_cache(&mut self) {
+        self.cache
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .results
+            .clear();
+    }
+
+    /// Creates a new table in read-only mode that can't be turned off, for
+    /// embedders that run untrusted input, which could send `.readonly off`.
+    pub fn new_readonly() -> Self {
+        Table {
+            readonly: true,
+            readonly_locked: true,
+            ..Table::new()
+        }
+    }
+
+    /// Turns the read-only mode on or off, while it's on every mutating
+    /// `Command` is rejected. A table from `new_readonly` can't turn it off.
+    pub fn set_readonly(&mut self, readonly: bool) -> Result<(), Error> {
+        if self.readonly_locked && !readonly {
+            return Err(Error::ReadOnly(
+                "Read-only mode. The table was created read-only".to_string(),
+            ));
+        }
+
+        self.readonly = readonly;
+
+        Ok(())
+    }
+
+    /// Returns if the `Table` is in read-only mode.
+    pub fn is_readonly(&self) -> bool {
+        self.readonly
+    }
+
+    /// Returns if the `Table` was created read-only by `new_readonly`.
+    pub fn is_readonly_locked(&self) -> bool {
+        self.readonly_locked
+    }
+
+    /// Returns the free text set by `.comment`, documenting what the `Table` is for.
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    /// Replaces the description of the `Table`.
+    pub fn set_description(&mut self, description: String) {
+        self.description = Some(description);
+    }
+
+    /// Reverses the last change of the `Row`s, every insert, update and
+    /// delete replaces what the previous one had left for it. It can only
+    /// be done once for each of them.
+    pub fn undo(&mut self) -> Result<(), Error> {
+        match self.undo.take() {
+            Some(Undo::Truncate(count)) => {
+                self.rows.truncate(count);
+                self.prune_interner();
+            }
+            Some(Undo::Rekey { rowid, from }) => {
+                if let Some(row) = self.rows.iter_mut().find(|row| row.rowid() == rowid) {
+                    row.set_id(from);
+                }
+            }
+            Some(Undo::Restore(rows)) => {
+                self.rows.extend(rows);
+                self.rows.sort_by_key(Row::rowid);
+            }
+            None => {
+                return Err(Error::SyntaxError(
+                    "Syntax error. There's nothing to undo".to_string(),
+                ))
+            }
+        }
+
+        self.clear_cache();
+
+        Ok(())
+    }
+
+    /// Replaces what `undo` reverses, the `Row`s of a replaced `Undo::Restore`
+    /// are dropped, so their values are pruned from the `Interner`.
+    fn set_undo(&mut self, undo: Undo) {
+        let replaced = self.undo.replace(undo);
+
+        if let Some(Undo::Restore(rows)) = replaced {
+            drop(rows);
+            self.prune_interner();
+        }
+    }
+
+    /// Forgets the interned values that no `Row` uses anymore.
+    fn prune_interner(&mut self) {
+        if let Some(interner) = &mut self.interner {
+            interner.prune();
+        }
+    }
+
+    /// Removes the `Row`s for which `predicate` returns `true`, returning how many
+    /// were removed. They're kept so that `undo` can put them back.
+    pub fn delete_rows<F>(&mut self, mut predicate: F) -> usize
+    where
+        F: FnMut(&Row) -> bool,
+    {
+        let (removed, rows) = mem::take(&mut self.rows)
+            .into_iter()
+            .partition(|row| predicate(row));
+
+        self.rows = rows;
+        self.clear_cache();
+
+        let count = removed.len();
+
+        if count > 0 {
+            self.set_undo(Undo::Restore(removed));
+        }
+
+        count
+    }
+
+    /// Changes the `id` of the `Row` with the `id` `from` to `to`, keeping its other
+    /// fields. If there's no such `Row`, or `to` is already taken, nothing changes.
+    /// Otherwise `undo` gives the same `Row` its `id` back, even if there are others with it.
+    pub fn rekey_row(&mut self, from: usize, to: usize) -> Result<(), Error> {
+        if from != to && self.find_row(to).is_some() {
+            return Err(Error::SyntaxError(format!(
+                "Syntax error. A row with id {} already exists",
+                to
+            )));
+        }
+
+        let row = self
+            .rows
+            .iter_mut()
+            .find(|row| row.id() == from)
+            .ok_or_else(|| {
+                Error::SyntaxError(format!("Syntax error. There's no row with id {}", from))
+            })?;
+
+        row.set_id(to);
+
+        let rowid = row.rowid();
+        self.set_undo(Undo::Rekey { rowid, from });
+        self.clear_cache();
+
+        Ok(())
+    }
+
+    /// Adds `domain` to the allowed email domains, once there's any
+    /// of them, only `Row`s with those domains pass `validate_row`.
+    pub fn allow_domain(&mut self, domain: &str) {
+        let domain = domain.to_ascii_lowercase();
+
+        if !self.allowed_domains.contains(&domain) {
+            self.allowed_domains.push(domain);
+        }
+    }
+
+    /// Adds a `validator` that runs on every `Row` passed to `validate_row`,
+    /// after the built-in checks and the validators added before it.
+    pub fn add_validator<F>(&mut self, validator: F)
+    where
+        F: Fn(&Row) -> Result<(), Error> + Send + Sync + 'static,
+    {
+        self.validators.push(Box::new(validator));
+    }
+
+    /// Checks a `Row` against the settings of the `Table` before it gets added
+    /// or updated: first the allowed email domains, compared ignoring ASCII
+    /// case, then every `RowValidator` in the order they were added.
+    pub fn validate_row(&self, row: &Row) -> Result<(), Error> {
+        if !self.allowed_domains.is_empty() {
+            let domain = row.email().rsplit('@').next().unwrap_or("");
+
+            let allowed = row.email().contains('@')
+                && self
+                    .allowed_domains
+                    .iter()
+                    .any(|allowed| allowed.eq_ignore_ascii_case(domain));
+
+            if !allowed {
+                return Err(Error::SyntaxError(format!(
+                    "Syntax error. Email domain of '{}' isn't allowed",
+                    row.email()
+                )));
+            }
+        }
+
+        self.validators.iter().try_for_each(|validator| {
+            validator(row)?;
+            self.check_deadline()
+        })
+    }
+
+    /// Returns how the values of an `insert` should be parsed, with
+    /// the default `email` and if long text values are truncated.
+    pub fn parse_options(&self) -> ParseOptions<'_> {
+        ParseOptions {
+            default_email: self.default_email.as_deref(),
+            truncate: self.truncate,
+        }
+    }
+
+    /// Turns truncating text values longer than their maximum on or off.
+    pub fn set_truncate(&mut self, truncate: bool) {
+        self.truncate = truncate;
+    }
+
+    /// Returns how long a single command can take, if there's a limit.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// Sets how long a single command can take, `None` removes the limit.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    /// Returns a `Timeout` error if the running command is past its deadline,
+    /// the loops that can take long call it as they go.
+    pub fn check_deadline(&self) -> Result<(), Error> {
+        match (DEADLINE.with(Cell::get), self.timeout) {
+            (Some(deadline), Some(timeout)) if Instant::now() > deadline => {
+                Err(Error::Timeout(format!(
+                    "Timeout. The command took longer than {} ms",
+                    timeout.as_millis()
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Replaces the default `email`, it's validated like any other `email`.
+    pub fn set_default_email(&mut self, email: &str) -> Result<(), Error> {
+        Row::new(0, "default", email)?;
+        self.default_email = Some(email.to_string());
+
+        Ok(())
+    }
+
+    /// Restarts the `Random` generator from `seed`, so that
+    /// the following samples can be reproduced.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.random = Mutex::new(Random::new(seed));
+    }
+
+    /// Picks up to `n` `Row`s with reservoir sampling, in one pass over
+    /// them, and returns their positions in `list_rows` in ascending order.
+    pub fn sample_rows(&self, n: usize) -> Vec<usize> {
+        let mut random = self.random.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut reservoir: Vec<usize> = vec![];
+
+        for index in 0..self.rows.len() {
+            if index < n {
+                reservoir.push(index);
+                continue;
+            }
+
+            let position = random.below(index + 1);
+
+            if position < n {
+                reservoir[position] = index;
+            }
+        }
+
+        reservoir.sort_unstable();
+
+        reservoir
+    }
+}
+
+/// Runs `f` as a single command that can take up to `timeout`, the deadline
+/// of the command it's nested in, if any, is put back after it.
+pub(crate) fn with_deadline<T>(timeout: Option<Duration>, f: impl FnOnce() -> T) -> T {
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    let outer_deadline = DEADLINE.with(|cell| cell.replace(deadline));
+
+    let result = f();
+
+    DEADLINE.with(|cell| cell.set(outer_deadline));
+
+    result
+}
+
+impl Default for Table {
+    fn default() -> Self {
+        Table::new()
+    }
+}
+
+/// Extension methods for embedding `scoolite`, so that `Row`s can be
+/// inserted from their values instead of an `insert` `String`.
+pub trait TableExt {
+    fn insert_values(&mut self, id: usize, username: &str, email: &str) -> Result<(), Error>;
+}
+
+impl TableExt for Table {
+    /// Builds a `Row` with `Row::new` and adds it to the `Table`, if it's valid.
+    fn insert_values(&mut self, id: usize, username: &str, email: &str) -> Result<(), Error> {
+        let row = Row::new(id, username, email)?;
+        self.validate_row(&row)?;
+        self.add_row(row);
+
+        Ok(())
+    }
 }